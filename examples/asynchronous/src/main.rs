@@ -2,7 +2,10 @@ use std::env;
 use ksoft::{
     Client,
     EventHandler as KSoftEvents,
-    model::bans::BanUpdate,
+    model::{
+        bans::BanUpdate,
+        images::Subreddit
+    },
     prelude::{SpanType, async_trait}
 };
 
@@ -11,7 +14,8 @@ async fn main() {
     let ksoft = Client::new(env::var("KSOFT_TOKEN").unwrap());
     ksoft.event_handler(EventHandler);
 
-    if let Ok(res) = ksoft.images.random_reddit("some subreddit", true, SpanType::Month).await {
+    let subreddit: Subreddit = "memes".parse().expect("Invalid subreddit name");
+    if let Ok(res) = ksoft.images.random_reddit(&subreddit, true, SpanType::Month).await {
         match res {
             Ok(reddit) => {
                 println!("Reddit image received! {:#?}", reddit);
@@ -4,7 +4,10 @@ use ksoft::{
         Client,
         EventHandler as KSoftEvents,
     },
-    model::bans::BanUpdate,
+    model::{
+        bans::BanUpdate,
+        images::Subreddit
+    },
     prelude::SpanType
 };
 
@@ -12,7 +15,8 @@ fn main() {
     let ksoft = Client::new(env::var("KSOFT_TOKEN").unwrap());
     ksoft.event_handler(EventHandler);
 
-    if let Ok(res) = ksoft.images.random_reddit("some subreddit", true, SpanType::Month) {
+    let subreddit: Subreddit = "memes".parse().expect("Invalid subreddit name");
+    if let Ok(res) = ksoft.images.random_reddit(&subreddit, true, SpanType::Month) {
         match res {
             Ok(reddit) => {
                 println!("Reddit image received! {:#?}", reddit);
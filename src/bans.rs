@@ -1,21 +1,25 @@
-use crate::{make_request, endpoint, model::*, HttpResult, EventHandler};
+use crate::{make_request, send_request, endpoint, model::*, HttpResult, EventHandler};
 use reqwest::{Client as HttpClient};
+use std::sync::Arc;
+use crate::cache::ResponseCache;
 use std::time::{SystemTime, UNIX_EPOCH};
 use crate::model::bans::*;
 use tracing::warn;
 
 pub struct Bans {
-    http: HttpClient
+    http: HttpClient,
+    pub(crate) response_cache: Option<Arc<ResponseCache>>
 }
 
 impl Bans {
     pub fn new(http_client: HttpClient) -> Self {
         Self {
             http: http_client,
+            response_cache: None
         }
     }
 
-    pub(crate) fn event_handler(&self, handler: impl EventHandler + Send + Sync + 'static) {
+    pub(crate) fn event_handler(&self, handler: impl EventHandler) {
         let client = self.http.clone();
         tokio::spawn(async move {
             let delay = tokio::time::Duration::from_secs(5 * 60);
@@ -35,7 +39,7 @@ impl Bans {
                         match res.json().await {
                             Ok(RawBanUpdate { data, timestamp }) => {
                                 last_check = timestamp;
-                                if data.len() >= 1 {
+                                if !data.is_empty() {
                                     handler.ban_updated(data).await;
                                 }
                             },
@@ -75,7 +79,7 @@ impl Bans {
             .query(&[("per_page", per_page)])
             .query(&[("page", page)]);
 
-        make_request::<BanList, BanError>(builder).await
+        make_request::<BanList, BanError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Shortcut to advanced_paginate() but with default parameters
@@ -114,8 +118,9 @@ impl Bans {
     ///     }
     /// }
     /// ```
+    #[allow(clippy::too_many_arguments)]
     pub async fn add<S: ToString>(&self,
-      user_id: u64,
+      user_id: impl UserId,
       reason: S,
       proof: S,
       moderator: Option<u64>,
@@ -128,7 +133,7 @@ impl Bans {
 
         let builder = self.http.post(endpoint("/bans/add").as_str())
             .form(&BanAddition {
-                user_id,
+                user_id: user_id.user_id(),
                 reason: reason.to_string(),
                 proof: proof.to_string(),
                 moderator,
@@ -137,7 +142,7 @@ impl Bans {
                 appeal_possible
             });
 
-        make_request::<BanAdditionResponse, BanError>(builder).await
+        make_request::<BanAdditionResponse, BanError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Check if user is banned ny its id
@@ -149,11 +154,11 @@ impl Bans {
     ///     //do something with the ban
     /// }
     /// ```
-    pub async fn check_ban(&self, user_id: u64) -> reqwest::Result<BanCheckResponse> {
-        let response = self.http.get(endpoint("/bans/check").as_str())
-            .query(&[("user", user_id)])
-            .send()
-            .await?;
+    pub async fn check_ban(&self, user_id: impl UserId) -> reqwest::Result<BanCheckResponse> {
+        let builder = self.http.get(endpoint("/bans/check").as_str())
+            .query(&[("user", user_id.user_id())]);
+
+        let response = send_request(builder, self.response_cache.as_deref()).await?;
 
         response.json::<BanCheckResponse>().await
     }
@@ -174,11 +179,11 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub async fn ban_info(&self, user_id: u64) -> HttpResult<BanInfoResponse, BanError> {
+    pub async fn ban_info(&self, user_id: impl UserId) -> HttpResult<BanInfoResponse, BanError> {
         let builder = self.http.get(endpoint("/bans/info").as_str())
-            .query(&[("user", user_id)]);
+            .query(&[("user", user_id.user_id())]);
 
-        make_request::<BanInfoResponse, BanError>(builder).await
+        make_request::<BanInfoResponse, BanError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Forces the deletion of an user ban. **Must have BAN_MANAGER permission on ksoft to use it**
@@ -197,12 +202,12 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub async fn delete_forcing(&self, user_id: u64) -> HttpResult<BanDeletionResponse, BanError> {
+    pub async fn delete_forcing(&self, user_id: impl UserId) -> HttpResult<BanDeletionResponse, BanError> {
         let builder = self.http.delete(endpoint("/bans/delete").as_str())
-            .query(&[("user", user_id)])
+            .query(&[("user", user_id.user_id())])
             .query(&[("force", true)]);
 
-        make_request::<BanDeletionResponse, BanError>(builder).await
+        make_request::<BanDeletionResponse, BanError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Deletes an user ban. **Must have BAN_MANAGER permission on ksoft to use it**
@@ -221,10 +226,10 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub async fn delete(&self, user_id: u64) -> HttpResult<BanDeletionResponse, BanError> {
+    pub async fn delete(&self, user_id: impl UserId) -> HttpResult<BanDeletionResponse, BanError> {
         let builder = self.http.delete(endpoint("/bans/delete").as_str())
-            .query(&[("user", user_id)]);
+            .query(&[("user", user_id.user_id())]);
 
-        make_request::<BanDeletionResponse, BanError>(builder).await
+        make_request::<BanDeletionResponse, BanError>(builder, self.response_cache.as_deref()).await
     }
 }
@@ -21,7 +21,7 @@ impl Bans {
         }
     }
 
-    pub(crate) fn event_handler(&self, handler: impl EventHandler + Send + Sync + 'static) {
+    pub(crate) fn event_handler(&self, handler: impl EventHandler) {
         let client = self.http.clone();
         thread::spawn(move || {
             let delay = std::time::Duration::from_secs(5 * 60);
@@ -40,7 +40,7 @@ impl Bans {
                         match res.json() {
                             Ok(RawBanUpdate { data, timestamp }) => {
                                 last_check = timestamp;
-                                if data.len() >= 1 {
+                                if !data.is_empty() {
                                     handler.ban_updated(data);
                                 }
                             },
@@ -119,8 +119,9 @@ impl Bans {
     ///     }
     /// }
     /// ```
+    #[allow(clippy::too_many_arguments)]
     pub fn add<S: ToString>(&self,
-      user_id: u64,
+      user_id: impl UserId,
       reason: S,
       proof: S,
       moderator: Option<u64>,
@@ -133,7 +134,7 @@ impl Bans {
 
         let builder = self.http.post(endpoint("/bans/add").as_str())
             .form(&BanAddition {
-                user_id,
+                user_id: user_id.user_id(),
                 reason: reason.to_string(),
                 proof: proof.to_string(),
                 moderator,
@@ -154,9 +155,9 @@ impl Bans {
     ///     //do something with the ban
     /// }
     /// ```
-    pub fn check_ban(&self, user_id: u64) -> reqwest::Result<BanCheckResponse> {
+    pub fn check_ban(&self, user_id: impl UserId) -> reqwest::Result<BanCheckResponse> {
         let response = self.http.get(endpoint("/bans/check").as_str())
-            .query(&[("user", user_id)])
+            .query(&[("user", user_id.user_id())])
             .send()?;
 
         response.json::<BanCheckResponse>()
@@ -178,9 +179,9 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub fn ban_info(&self, user_id: u64) -> HttpResult<BanInfoResponse, BanError> {
+    pub fn ban_info(&self, user_id: impl UserId) -> HttpResult<BanInfoResponse, BanError> {
         let builder = self.http.get(endpoint("/bans/info").as_str())
-            .query(&[("user", user_id)]);
+            .query(&[("user", user_id.user_id())]);
 
         make_request::<BanInfoResponse, BanError>(builder)
     }
@@ -201,9 +202,9 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub fn delete_forcing(&self, user_id: u64) -> HttpResult<BanDeletionResponse, BanError> {
+    pub fn delete_forcing(&self, user_id: impl UserId) -> HttpResult<BanDeletionResponse, BanError> {
         let builder = self.http.delete(endpoint("/bans/delete").as_str())
-            .query(&[("user", user_id)])
+            .query(&[("user", user_id.user_id())])
             .query(&[("force", true)]);
 
         make_request::<BanDeletionResponse, BanError>(builder)
@@ -225,9 +226,9 @@ impl Bans {
     ///     }
     /// }
     /// ```
-    pub fn delete(&self, user_id: u64) -> HttpResult<BanDeletionResponse, BanError> {
+    pub fn delete(&self, user_id: impl UserId) -> HttpResult<BanDeletionResponse, BanError> {
         let builder = self.http.delete(endpoint("/bans/delete").as_str())
-            .query(&[("user", user_id)]);
+            .query(&[("user", user_id.user_id())]);
 
         make_request::<BanDeletionResponse, BanError>(builder)
     }
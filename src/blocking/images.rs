@@ -1,24 +1,55 @@
-use reqwest::blocking::{Client as HttpClient};
+use reqwest::{blocking::Client as HttpClient, header::CONTENT_TYPE};
+use std::io::Read;
 use crate::{
     endpoint,
+    endpoint_segments,
     model::*,
-    HttpResult
+    HttpResult,
+    ApiResponse,
+    DownloadError
+};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant}
 };
 use super::make_request;
 use crate::model::images::*;
 use crate::prelude::*;
 
+/// Time after which the cached tag registry is fetched again
+pub const DEFAULT_TAGS_REFRESH: Duration = Duration::from_secs(60 * 60);
+
+type CachedTags = Arc<RwLock<Option<(Instant, Arc<TagRegistry>)>>>;
+
+#[derive(Clone)]
 pub struct Images {
-    http: HttpClient
+    http: HttpClient,
+    download: HttpClient,
+    tags: CachedTags,
+    tags_refresh: Duration,
 }
 
 impl Images {
     pub fn new(http_client: HttpClient) -> Self {
+        Self::with_download_client(http_client, download_client())
+    }
+
+    /// Creates the images client downloading images with the given client, it must not send the
+    /// KSoft token, as images are hosted by third parties
+    pub fn with_download_client(http_client: HttpClient, download_client: HttpClient) -> Self {
         Self {
-            http: http_client
+            http: http_client,
+            download: download_client,
+            tags: Arc::new(RwLock::new(None)),
+            tags_refresh: DEFAULT_TAGS_REFRESH,
         }
     }
 
+    /// Sets how often the cached tag registry is fetched again
+    pub fn set_tags_refresh(&mut self, interval: Duration) {
+        self.tags_refresh = interval;
+    }
+
     ///Gets a random image based in a given tag
     ///
     /// # Example
@@ -70,17 +101,21 @@ impl Images {
     /// }
     /// ```
     pub fn random_aww(&self) -> reqwest::Result<RedditImage>{
-        return self.http.get(endpoint("/images/random-aww").as_str())
+        self.http.get(endpoint("/images/random-aww").as_str())
             .send()?
             .json::<RedditImage>()
     }
 
     ///Gets a random post from a given subreddit
     ///
+    /// The name is validated beforehand with [`Subreddit::new`](crate::model::images::Subreddit::new),
+    /// which also accepts `r/memes` and `/r/memes`
+    ///
     /// # Example
     ///
     /// ```rust,ignore
-    /// if let Ok(res) = client.images.random_reddit("Technology", true, SpanType::Day) {
+    /// let subreddit: Subreddit = "Technology".parse()?;
+    /// if let Ok(res) = client.images.random_reddit(&subreddit, true, SpanType::Day) {
     ///     match res {
     ///         Ok(red) => {
     ///             //do something with the reddit image
@@ -91,16 +126,48 @@ impl Images {
     ///     }
     /// }
     /// ```
-    pub fn random_reddit(&self, subreddit: impl ToString, remove_nsfw: bool, span: SpanType) -> HttpResult<RedditImage, ImageError>{
-        if subreddit.to_string().is_empty() { panic!("You have to specify a subreddit to search in") }
-
-        let builder = self.http.get(endpoint(format!("/images/rand-reddit/{}", subreddit.to_string())).as_str())
+    pub fn random_reddit(&self, subreddit: &Subreddit, remove_nsfw: bool, span: SpanType) -> HttpResult<RedditImage, ImageError>{
+        let builder = self.http.get(endpoint_segments("/images/rand-reddit", &[subreddit.name()]).as_str())
             .query(&[("remove_nsfw", remove_nsfw)])
             .query(&[("span", span.to_string())]);
 
         make_request::<RedditImage, ImageError>(builder)
     }
 
+    ///Gets a random post from a subreddit matching the query filters
+    ///
+    /// Posts are requested until one matches or the query retries run out, returning None in that case
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = RedditQuery::new("aww".parse()?).min_upvotes(1000).media_type(MediaType::Gif);
+    /// if let Ok(res) = client.images.reddit(&query) {
+    ///     match res {
+    ///         Ok(Some(red)) => {
+    ///             //do something with the reddit image
+    ///         },
+    ///         Ok(None) => {
+    ///             //no post matched the filters
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <ImageError> struct
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn reddit(&self, query: &RedditQuery) -> HttpResult<Option<RedditImage>, ImageError> {
+        for _ in 0..=query.retries {
+            match self.random_reddit(&query.subreddit, query.remove_nsfw, query.span)? {
+                Ok(image) if query.matches(&image) => return Ok(Ok(Some(image))),
+                Ok(_) => continue,
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        Ok(Ok(None))
+    }
+
     ///Gets a random WikiHow image
     ///
     /// # Example
@@ -111,7 +178,7 @@ impl Images {
     /// }
     /// ```
     pub fn random_wikihow(&self, nsfw: bool) -> reqwest::Result<WikiHowImage> {
-        return self.http.get(endpoint("/images/random-wikihow").as_str())
+        self.http.get(endpoint("/images/random-wikihow").as_str())
             .query(&[("nsfw", nsfw)])
             .send()?
             .json::<WikiHowImage>()
@@ -126,7 +193,7 @@ impl Images {
     /// }
     /// ```
     pub fn get_tags(&self) -> reqwest::Result<TagList> {
-        return self.http.get(endpoint("/images/tags").as_str())
+        self.http.get(endpoint("/images/tags").as_str())
             .send()?
             .json::<TagList>()
     }
@@ -150,7 +217,7 @@ impl Images {
     pub fn get_image(&self, sf: impl ToString) -> HttpResult<Image, ImageError> {
         if sf.to_string().is_empty() { panic!("Snowflake id cannot be empty") }
 
-        let builder = self.http.get(endpoint(format!("/images/image/{}", sf.to_string())).as_str());
+        let builder = self.http.get(endpoint_segments("/images/image", &[&sf.to_string()]).as_str());
 
         make_request::<Image, ImageError>(builder)
     }
@@ -167,11 +234,32 @@ impl Images {
     pub fn get_tag(&self, tag: impl ToString) -> reqwest::Result<TagList> {
         if tag.to_string().is_empty() { panic!("Tag cannot be empty") }
 
-        self.http.get(endpoint(format!("/images/tags/{}", tag.to_string())).as_str())
+        self.http.get(endpoint_segments("/images/tags", &[&tag.to_string()]).as_str())
             .send()?
             .json::<TagList>()
     }
 
+    ///Searches tags by a partial name, best matches first, using the same lookup as [`get_tag`](Self::get_tag)
+    ///
+    /// An empty query lists every tag from [`get_tags`](Self::get_tags) instead
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(tags) = client.images.search_tags("dog", false) {
+    ///     //offer the tags as autocomplete choices
+    /// }
+    /// ```
+    pub fn search_tags(&self, query: impl ToString, nsfw: bool) -> reqwest::Result<Vec<TagModel>> {
+        let query = query.to_string();
+        let list = match query.trim() {
+            "" => self.get_tags()?,
+            query => self.get_tag(query)?
+        };
+
+        Ok(list.ranked(query, nsfw))
+    }
+
     ///Get a random NSFW image
     ///
     /// # Example
@@ -187,4 +275,171 @@ impl Images {
             .send()?
             .json::<RedditImage>()
     }
+
+    ///Downloads an image, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download(&image) {
+    ///     //upload file.bytes as an attachment named format!("image.{}", file.extension)
+    /// }
+    /// ```
+    pub fn download(&self, image: &Image) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.url, DEFAULT_DOWNLOAD_LIMIT)
+    }
+
+    ///Downloads the image of a reddit post, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_reddit(&meme) {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub fn download_reddit(&self, image: &RedditImage) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.image_url, DEFAULT_DOWNLOAD_LIMIT)
+    }
+
+    ///Downloads a WikiHow image, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_wikihow(&wiki_image) {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub fn download_wikihow(&self, image: &WikiHowImage) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.url, DEFAULT_DOWNLOAD_LIMIT)
+    }
+
+    ///Downloads a file from a given url, failing if it is larger than `limit` bytes
+    ///
+    /// The request is sent by a separate http client without the KSoft authorization header,
+    /// so the token is never leaked to third party hosts
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_url(&meme.image_url, 25 * 1024 * 1024) {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub fn download_url(&self, url: impl AsRef<str>, limit: usize) -> Result<ImageDownload, DownloadError> {
+        let response = self.download.get(url.as_ref()).send()?;
+
+        if !response.status().is_success() {
+            return Err(DownloadError::UnsuccessfulResponse(response.status().as_u16()));
+        }
+        if response.content_length().is_some_and(|len| len > limit as u64) {
+            return Err(DownloadError::TooLarge(limit));
+        }
+
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .map(ToString::to_string);
+
+        let mut bytes = Vec::new();
+        response.take(limit as u64 + 1).read_to_end(&mut bytes)
+            .map_err(DownloadError::ReadFailed)?;
+        if bytes.len() > limit {
+            return Err(DownloadError::TooLarge(limit));
+        }
+
+        Ok(ImageDownload::new(bytes, content_type.as_deref()))
+    }
+
+    ///Gets the registry of available tags, it is cached and fetched again once it gets older than the refresh interval
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(registry) = client.images.tag_registry() {
+    ///     let suggestions = registry.suggest("dgoe", false);
+    /// }
+    /// ```
+    pub fn tag_registry(&self) -> reqwest::Result<Arc<TagRegistry>> {
+        if let Some((fetched_at, registry)) = &*self.tags.read().expect("Tag registry lock poisoned") {
+            if fetched_at.elapsed() < self.tags_refresh {
+                return Ok(Arc::clone(registry));
+            }
+        }
+
+        let registry = Arc::new(TagRegistry::from(self.get_tags()?));
+        *self.tags.write().expect("Tag registry lock poisoned") = Some((Instant::now(), Arc::clone(&registry)));
+
+        Ok(registry)
+    }
+
+    ///Checks a tag locally against the cached tag registry
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(res) = client.images.tag("doge", false) {
+    ///     match res {
+    ///         Ok(tag) => {
+    ///             let image = client.images.random_image(tag, false);
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <TagError>, it may contain suggestions
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn tag(&self, tag: impl AsRef<str>, nsfw: bool) -> reqwest::Result<ApiResponse<Tag, TagError>> {
+        Ok(self.tag_registry()?.check(tag, nsfw))
+    }
+}
+
+/// Client without the KSoft authorization header, used to download images from third party hosts
+pub(crate) fn download_client() -> HttpClient {
+    HttpClient::builder()
+        .user_agent("KSoft.rs")
+        .build()
+        .expect("Something went wrong when creating download client")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocking::Client;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread
+    };
+
+    /// Serves a single image, returning the request it received
+    fn image_host() -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                if read == 0 { break }
+                request.extend_from_slice(&buf[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\n\x89PNG").unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn downloads_do_not_send_token() {
+        let (url, server) = image_host();
+        let client = Client::new("secret-token");
+        let download = client.images.download_url(&url, 1024).unwrap();
+        let request = server.join().unwrap();
+
+        assert_eq!(download.bytes, b"\x89PNG");
+        assert!(request.starts_with("get /image.png"), "{}", request);
+        assert!(!request.contains("authorization"), "{}", request);
+        assert!(!request.contains("secret-token"), "{}", request);
+    }
 }
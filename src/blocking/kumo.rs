@@ -4,7 +4,8 @@ use crate::{
     model::*,
     HttpResult
 };
-use crate::model::kumo::*;
+use crate::model::{currency::*, kumo::*};
+use serde::de::DeserializeOwned;
 use super::make_request;
 
 pub struct Kumo {
@@ -18,36 +19,85 @@ impl Kumo {
         }
     }
 
-    ///Get data from a given IP address
+    ///Get data from a given IPv4 or IPv6 address, given as a `std::net` address or a string
+    ///
+    /// Invalid addresses and addresses that are not public, like private or loopback ones,
+    /// are rejected with a [`GeoIpError`](GeoIpError) without making a request
     ///
     /// # Example
     ///
     /// ```rust,ignore
-    /// if let Ok(res) = client.kumo.geoip("AmazingNonExistingIP") {
+    /// if let Ok(res) = client.kumo.geoip("2001:4860:4860::8888") {
     ///     match res {
     ///         Ok(ip) => {
     ///             //do something with ip info
     ///         },
     ///         Err(why) => {
-    ///             //do something with the <KumoError> struct
+    ///             //do something with the <GeoIpError> enum
     ///         }
     ///     }
     /// }
     /// ```
-    pub fn geoip(&self, ip: impl ToString) -> HttpResult<GeoIPResponse, KumoError> {
-        let ip_parsed = ip.to_string().parse::<std::net::Ipv4Addr>().expect("Cannot parse as ip");
+    pub fn geoip(&self, ip: impl IntoIpAddr) -> HttpResult<GeoIPResponse, GeoIpError> {
+        let ip = match ip.into_ip_addr().and_then(GeoIpError::check) {
+            Ok(ip) => ip,
+            Err(why) => return Ok(Err(why))
+        };
 
         let builder = self.http.get(endpoint("/kumo/geoip").as_str())
-            .query(&[("ip", ip_parsed.to_string())]);
+            .query(&[("ip", ip.to_string())]);
 
-        make_request::<GeoIPResponse, KumoError>(builder)
+        Ok(make_request::<GeoIPResponse, KumoError>(builder)?.map_err(GeoIpError::Api))
     }
 
-    ///Performs currency conversion
+    ///Find a location by its name or address specifying custom options
+    ///
+    /// Empty queries are rejected with a [`GisError`](GisError) without making a request
     ///
     /// # Example
+    ///
     /// ```rust,ignore
-    /// if let Ok(res) = client.kumo.convert_currency(120.0, "USD", "EUR") {
+    /// let options = GisOptions::new().more(true).map_zoom(12);
+    /// if let Ok(res) = client.kumo.advanced_gis("Madrid", &options) {
+    ///     match res {
+    ///         Ok(gis) => {
+    ///             //do something with gis.data
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <GisError> enum
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn advanced_gis(&self, query: impl AsRef<str>, options: &GisOptions) -> HttpResult<GisResponse, GisError> {
+        let query = query.as_ref().trim();
+        if query.is_empty() { return Ok(Err(GisError::EmptyQuery)) }
+
+        let builder = self.http.get(endpoint("/kumo/gis").as_str())
+            .query(&options.query(query));
+
+        Ok(make_request::<GisResponse, KumoError>(builder)?.map_err(GisError::Api))
+    }
+
+    ///Shortcut to advanced_gis() but with default options
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(gis)) = client.kumo.gis("Madrid") {
+    ///     //do something with gis.data
+    /// }
+    /// ```
+    pub fn gis(&self, query: impl AsRef<str>) -> HttpResult<GisResponse, GisError> {
+        self.advanced_gis(query, &GisOptions::default())
+    }
+
+    ///Performs currency conversion, amounts are sent as exact decimals
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let amount: Amount = "120.50".parse()?;
+    /// if let Ok(res) = client.kumo.convert_currency(amount, Currency::USD, Currency::EUR) {
     ///     match res {
     ///         Ok(conversion) => {
     ///             //do something with conversion info
@@ -58,10 +108,116 @@ impl Kumo {
     ///     }
     /// }
     /// ```
-    pub fn convert_currency<C: ToString>(&self, value: f64, from: C, to: C) -> HttpResult<CurrencyConversionResponse, KumoError> {
+    pub fn convert_currency(&self, value: impl Into<Amount>, from: Currency, to: Currency) -> HttpResult<CurrencyConversionResponse, KumoError> {
         let builder = self.http.get(endpoint("/kumo/currency").as_str())
-            .query(&[("from", from.to_string()), ("to", to.to_string()), ("value", value.to_string())]);
+            .query(&[("from", from.to_string()), ("to", to.to_string()), ("value", value.into().to_string())]);
 
         make_request::<CurrencyConversionResponse, KumoError>(builder)
     }
+
+    ///Converts an amount into several currencies, one after another
+    ///
+    /// Conversions are returned in the same order as the target currencies,
+    /// the first error returned by the API is returned instead
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let amount: Amount = "19.99".parse()?;
+    /// if let Ok(Ok(conversions)) = client.kumo.convert_currency_many(amount, Currency::USD, [Currency::EUR, Currency::GBP]) {
+    ///     for (currency, conversion) in conversions {
+    ///         //do something with conversion info
+    ///     }
+    /// }
+    /// ```
+    pub fn convert_currency_many<I>(&self, value: impl Into<Amount>, from: Currency, to: I) -> HttpResult<Vec<(Currency, CurrencyConversionResponse)>, KumoError>
+    where
+        I: IntoIterator<Item = Currency>
+    {
+        let value = value.into();
+        let mut conversions = Vec::new();
+        for currency in to {
+            match self.convert_currency(value, from, currency)? {
+                Ok(conversion) => conversions.push((currency, conversion)),
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        Ok(Ok(conversions))
+    }
+
+    ///Get the current weather of a location specifying custom units and language
+    ///
+    /// Empty locations are rejected with a [`WeatherError`](WeatherError) without making a request
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = WeatherOptions::new().units(Units::Us).language("es");
+    /// if let Ok(res) = client.kumo.advanced_weather("Madrid", &options) {
+    ///     match res {
+    ///         Ok(weather) => {
+    ///             //do something with weather.data
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <WeatherError> enum
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn advanced_weather(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.weather_request("/kumo/weather/easy", location.as_ref(), options)
+    }
+
+    ///Shortcut to advanced_weather() but with SI units and the default language
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(weather)) = client.kumo.weather("Madrid") {
+    ///     //do something with weather.data
+    /// }
+    /// ```
+    pub fn weather(&self, location: impl AsRef<str>) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.advanced_weather(location, &WeatherOptions::default())
+    }
+
+    ///Get the current weather report of a location
+    pub fn weather_currently(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.weather_request("/kumo/weather/currently", location.as_ref(), options)
+    }
+
+    ///Get the weather forecast of a location for the next hour, minute by minute
+    pub fn weather_minutely(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/minutely", location.as_ref(), options)
+    }
+
+    ///Get the weather forecast of a location for the next two days, hour by hour
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(forecast)) = client.kumo.weather_hourly("Madrid", &WeatherOptions::default()) {
+    ///     for hour in forecast.data.data {
+    ///         //do something with the hour
+    ///     }
+    /// }
+    /// ```
+    pub fn weather_hourly(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/hourly", location.as_ref(), options)
+    }
+
+    ///Get the weather forecast of a location for the next week, day by day
+    pub fn weather_daily(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/daily", location.as_ref(), options)
+    }
+
+    fn weather_request<T: DeserializeOwned>(&self, path: &str, location: &str, options: &WeatherOptions) -> HttpResult<WeatherResponse<T>, WeatherError> {
+        let location = location.trim();
+        if location.is_empty() { return Ok(Err(WeatherError::EmptyLocation)) }
+
+        let builder = self.http.get(endpoint(path).as_str())
+            .query(&options.query(location));
+
+        Ok(make_request::<WeatherResponse<T>, KumoError>(builder)?.map_err(WeatherError::Api))
+    }
 }
@@ -8,13 +8,14 @@ pub mod bans;
 pub mod kumo;
 pub mod music;
 use self::{
-    images::Images,
+    images::{download_client, Images},
     bans::Bans,
     kumo::Kumo,
     music::Music,
     super::model::bans::BanUpdate
 };
 
+/// Blocking client, it does not cache responses, use the asynchronous client for that
 pub struct Client {
     pub token: String,
     pub images: Images,
@@ -36,7 +37,7 @@ impl Client {
 
         Self {
             token: token.to_string(),
-            images: Images::new(http_client.clone()),
+            images: Images::with_download_client(http_client.clone(), download_client()),
             bans: Bans::new(http_client.clone()),
             kumo: Kumo::new(http_client.clone()),
             music: Music::new(http_client.clone()),
@@ -65,16 +66,16 @@ impl Client {
     ///         println!("Ban update received: {:#?}", data);
     ///     }
     /// }
-    pub fn event_handler(&self, handler: impl EventHandler + Send + Sync + 'static ) {
+    pub fn event_handler(&self, handler: impl EventHandler ) {
         self.bans.event_handler(handler);
     }
 }
 
-pub(self) fn make_request<S: DeserializeOwned, E: DeserializeOwned>(c: RequestBuilder) -> HttpResult<S, E> {
+fn make_request<S: DeserializeOwned, E: DeserializeOwned>(c: RequestBuilder) -> HttpResult<S, E> {
     let response = c.send()?;
 
-    return match response.status().as_u16() {
-        c if c == 429u16 => Err(HttpError::RateLimited),
+    match response.status().as_u16() {
+        429u16 => Err(HttpError::RateLimited),
         c if c >= 500u16 => Err(HttpError::InternalServerError(response.text()?)),
         200u16 => {
             let data = response.json::<S>()?;
@@ -1,4 +1,5 @@
 use reqwest::blocking::{Client as HttpClient};
+use std::collections::HashMap;
 use crate::{
     endpoint,
     model::*,
@@ -67,6 +68,37 @@ impl Music {
         self.advanced_lyrics(query, false, 10)
     }
 
+    ///Searches lyrics with a query, keeping the relevant results, most relevant first
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = LyricsQuery::new("Luis Fonsi - Despacito (Official Video)").min_search_score(5.0);
+    /// if let Ok(results) = client.music.search_lyrics(&query) {
+    ///     //do something with the results
+    /// }
+    /// ```
+    pub fn search_lyrics(&self, query: &LyricsQuery) -> reqwest::Result<Vec<LyricsData>> {
+        let lyrics = self.advanced_lyrics(query.search_text(), query.text_only, query.limit)?;
+
+        Ok(query.filter(lyrics.data))
+    }
+
+    ///Gets the lyrics that best match a query
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Some(lyrics)) = client.music.best_lyrics(&LyricsQuery::new(video_title)) {
+    ///     //do something with the lyrics
+    /// }
+    /// ```
+    pub fn best_lyrics(&self, query: &LyricsQuery) -> reqwest::Result<Option<LyricsData>> {
+        let lyrics = self.advanced_lyrics(query.search_text(), query.text_only, query.limit)?;
+
+        Ok(query.best_match(&lyrics.data).cloned())
+    }
+
     ///Get recommendations of songs with given query specifying custom parameters
     ///
     /// **You need a premium plan to use this endpoint**
@@ -86,21 +118,56 @@ impl Music {
     ///     }
     // }
     /// ```
-    pub fn advanced_recommendations(&self, mut provider: ProviderType, youtube_token: Option<String>, limit: Option<u32>, recommend_type: Option<String>) -> HttpResult<MusicRecommendationsResponse, MusicError>{
+    pub fn advanced_recommendations(&self, mut provider: ProviderType, youtube_token: Option<String>, limit: Option<u32>, recommend_type: Option<RecommendType>) -> HttpResult<MusicRecommendationsResponse, MusicError>{
         let track_vec = provider.extract();
 
-        if track_vec.len() < 1 { panic!("Vector contents cannot be empty") }
+        if track_vec.is_empty() { panic!("Vector contents cannot be empty") }
 
-        let payload = MusicRecommendations {
+        self.recommend(&MusicRecommendations {
             tracks: track_vec,
             provider: provider.to_string(),
             youtube_token,
             limit,
-            recommend_type
-        };
+            recommend_type: recommend_type.map(|t| t.to_string())
+        })
+    }
 
+    ///Get recommendations for a whole playlist, sending its tracks in chunks one after another
+    ///
+    /// Recommendations of every chunk are de-duplicated and ranked by how many chunks recommended them,
+    /// the first error returned by the API is returned instead
+    ///
+    /// **You need a premium plan to use this endpoint**
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = PlaylistRecommendations::new(ProviderType::SpotifyIDs(playlist));
+    /// if let Ok(Ok(tracks)) = client.music.playlist_recommendations(&query) {
+    ///     for ranked in tracks {
+    ///         //do something with ranked.track
+    ///     }
+    /// }
+    /// ```
+    pub fn playlist_recommendations(&self, query: &PlaylistRecommendations) -> HttpResult<Vec<RankedTrack>, MusicError> {
+        let payloads = query.payloads();
+
+        if payloads.is_empty() { panic!("Vector contents cannot be empty") }
+
+        let mut tracks = Vec::new();
+        for payload in &payloads {
+            match self.recommend(payload)? {
+                Ok(response) => tracks.extend(response.tracks),
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        Ok(Ok(RankedTrack::rank(tracks)))
+    }
+
+    fn recommend(&self, payload: &MusicRecommendations) -> HttpResult<MusicRecommendationsResponse, MusicError> {
         let builder = self.http.post(endpoint("/music/recommendations").as_str())
-            .json(&payload);
+            .json(payload);
 
         make_request::<MusicRecommendationsResponse, MusicError>(builder)
     }
@@ -190,4 +257,40 @@ impl Music {
 
         make_request::<Track, MusicError>(builder)
     }
+
+    ///Get an artist along with all its albums and tracks
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(discography)) = client.music.discography(1398u64) {
+    ///     for album in discography.albums {
+    ///         //do something with album.album and album.tracks
+    ///     }
+    /// }
+    /// ```
+    pub fn discography(&self, artist_id: impl Into<u64>) -> HttpResult<Discography, MusicError> {
+        let artist = match self.artist(artist_id)? {
+            Ok(artist) => artist,
+            Err(why) => return Ok(Err(why))
+        };
+
+        let mut albums = Vec::with_capacity(artist.albums.len());
+        for album in &artist.albums {
+            match self.album(album.id)? {
+                Ok(album) => albums.push(album),
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        let mut tracks = HashMap::new();
+        for id in Discography::track_ids(&artist, &albums) {
+            match self.track(id)? {
+                Ok(track) => { tracks.insert(id, track); },
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        Ok(Ok(Discography::assemble(artist, albums, tracks)))
+    }
 }
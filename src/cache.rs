@@ -0,0 +1,602 @@
+//! Response caching
+//!
+//! Responses are cached by the asynchronous client through `Client::with_cache`, or `Images::with_cache`
+//! for an images client created on its own, the blocking client always hits the network
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    time::{Duration, Instant}
+};
+
+/// Counters of a cache, `entries` is the number of entries stored when the stats were taken
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize
+}
+
+impl CacheStats {
+    /// Ratio of lookups served from the cache, 0 if there were no lookups
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64
+        }
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+    last_used: u64
+}
+
+/// Least recently used cache whose entries also expire after a time to live
+pub struct LruCache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<K, Entry<V>>,
+    usage: BTreeMap<u64, K>,
+    tick: u64,
+    stats: CacheStats
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl,
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default()
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.expires_at <= Instant::now(),
+            None => {
+                self.stats.misses += 1;
+                return None;
+            }
+        };
+
+        if expired {
+            self.remove(key);
+            self.stats.misses += 1;
+            return None;
+        }
+
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.usage.remove(&entry.last_used);
+        entry.last_used = self.tick;
+        self.usage.insert(self.tick, key.clone());
+
+        self.stats.hits += 1;
+        Some(entry.value.clone())
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.insert_with_ttl(key, value, self.ttl);
+    }
+
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) {
+        self.remove(&key);
+
+        while self.entries.len() >= self.capacity {
+            match self.usage.iter().next().map(|(tick, key)| (*tick, key.clone())) {
+                Some((tick, oldest)) => {
+                    self.usage.remove(&tick);
+                    self.entries.remove(&oldest);
+                    self.stats.evictions += 1;
+                },
+                None => break
+            }
+        }
+
+        let now = Instant::now();
+        self.tick += 1;
+        self.usage.insert(self.tick, key.clone());
+        self.entries.insert(key, Entry {
+            value,
+            expires_at: now.checked_add(ttl).unwrap_or(now + Duration::from_secs(100 * 365 * 24 * 60 * 60)),
+            last_used: self.tick
+        });
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.usage.remove(&entry.last_used);
+        Some(entry.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(feature = "default")]
+pub use self::backend::*;
+
+#[cfg(feature = "default")]
+mod backend {
+    use async_trait::async_trait;
+    use reqwest::{Method, Request};
+    use std::{
+        convert::TryInto,
+        io,
+        path::PathBuf,
+        sync::Mutex,
+        time::{Duration, SystemTime, UNIX_EPOCH}
+    };
+    use super::{CacheStats, LruCache};
+
+    /// Storage for serialized KSoft responses, keyed by endpoint and query
+    ///
+    /// Implement it to share a response cache between processes, through Redis for example
+    #[async_trait]
+    pub trait CacheBackend: Send + Sync + 'static {
+        /// Gets a stored response, it must not return expired responses
+        async fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+        /// Stores a response that should expire after `ttl`
+        async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration);
+
+        async fn remove(&self, _key: &str) {}
+    }
+
+    /// Lets the caller keep a handle to the backend, to read [`MemoryBackend::stats`] for example
+    #[async_trait]
+    impl<B: CacheBackend + ?Sized> CacheBackend for std::sync::Arc<B> {
+        async fn get(&self, key: &str) -> Option<Vec<u8>> {
+            (**self).get(key).await
+        }
+
+        async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+            (**self).set(key, value, ttl).await
+        }
+
+        async fn remove(&self, key: &str) {
+            (**self).remove(key).await
+        }
+    }
+
+    /// In-memory cache backend, evicting the least recently used responses when full
+    pub struct MemoryBackend {
+        cache: Mutex<LruCache<String, Vec<u8>>>
+    }
+
+    impl MemoryBackend {
+        /// Default time to live, only used if a response is stored without one
+        const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+        pub fn new(capacity: usize) -> Self {
+            Self {
+                cache: Mutex::new(LruCache::new(capacity, Self::DEFAULT_TTL))
+            }
+        }
+
+        pub fn stats(&self) -> CacheStats {
+            self.cache.lock().expect("Cache lock poisoned").stats()
+        }
+    }
+
+    #[async_trait]
+    impl CacheBackend for MemoryBackend {
+        async fn get(&self, key: &str) -> Option<Vec<u8>> {
+            self.cache.lock().expect("Cache lock poisoned").get(&key.to_string())
+        }
+
+        async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+            self.cache.lock().expect("Cache lock poisoned").insert_with_ttl(key.to_string(), value, ttl);
+        }
+
+        async fn remove(&self, key: &str) {
+            self.cache.lock().expect("Cache lock poisoned").remove(&key.to_string());
+        }
+    }
+
+    /// Cache backend storing every response in its own file inside a directory,
+    /// it can be shared by processes on the same machine
+    pub struct FileBackend {
+        dir: PathBuf
+    }
+
+    impl FileBackend {
+        /// Creates the backend, creating the directory if it does not exist
+        pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+            let dir = dir.into();
+            std::fs::create_dir_all(&dir)?;
+
+            Ok(Self {
+                dir
+            })
+        }
+
+        fn path(&self, key: &str) -> PathBuf {
+            // FNV-1a, stable across builds so every process agrees on file names
+            let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+            self.dir.join(format!("{:016x}.cache", hash))
+        }
+
+        fn now() -> u64 {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        }
+    }
+
+    // File layout: expiration timestamp (u64), key length (u32), key, response
+    #[async_trait]
+    impl CacheBackend for FileBackend {
+        async fn get(&self, key: &str) -> Option<Vec<u8>> {
+            let path = self.path(key);
+            let data = tokio::fs::read(&path).await.ok()?;
+
+            let expires_at = u64::from_be_bytes(data.get(..8)?.try_into().ok()?);
+            let key_len = u32::from_be_bytes(data.get(8..12)?.try_into().ok()?) as usize;
+            if data.get(12..12 + key_len)? != key.as_bytes() {
+                return None;
+            }
+            if expires_at <= Self::now() {
+                let _ = tokio::fs::remove_file(&path).await;
+                return None;
+            }
+
+            Some(data[12 + key_len..].to_vec())
+        }
+
+        async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+            let expires_at = Self::now().saturating_add(ttl.as_secs());
+            let mut data = Vec::with_capacity(12 + key.len() + value.len());
+            data.extend_from_slice(&expires_at.to_be_bytes());
+            data.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            data.extend_from_slice(key.as_bytes());
+            data.extend_from_slice(&value);
+
+            // Written to a temporary file first so readers never see a partial response
+            let path = self.path(key);
+            let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+            if tokio::fs::write(&tmp, data).await.is_ok() && tokio::fs::rename(&tmp, &path).await.is_err() {
+                let _ = tokio::fs::remove_file(&tmp).await;
+            }
+        }
+
+        async fn remove(&self, key: &str) {
+            let _ = tokio::fs::remove_file(self.path(key)).await;
+        }
+    }
+
+    /// Time to live of the responses of each endpoint, endpoints are matched by the longest path prefix
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let policy = CachePolicy::default()
+    ///     .endpoint("/lyrics", Some(Duration::from_secs(7 * 24 * 60 * 60)))
+    ///     .endpoint("/bans", None);
+    /// ```
+    #[derive(Clone, Debug)]
+    pub struct CachePolicy {
+        default_ttl: Option<Duration>,
+        endpoints: Vec<(String, Option<Duration>)>
+    }
+
+    impl CachePolicy {
+        /// Creates a policy without endpoint rules, `default_ttl` applies to every endpoint, None to not cache them
+        pub fn new(default_ttl: Option<Duration>) -> Self {
+            Self {
+                default_ttl,
+                endpoints: Vec::new()
+            }
+        }
+
+        /// Sets the time to live of the endpoints starting with `path`, None to never cache them
+        pub fn endpoint(mut self, path: impl ToString, ttl: Option<Duration>) -> Self {
+            let path = path.to_string();
+            self.endpoints.retain(|(p, _)| *p != path);
+            self.endpoints.push((path, ttl));
+            self
+        }
+
+        pub fn ttl_for(&self, path: &str) -> Option<Duration> {
+            self.endpoints.iter()
+                .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map_or(self.default_ttl, |(_, ttl)| *ttl)
+        }
+    }
+
+    impl Default for CachePolicy {
+        /// Caches lyrics and music metadata for days, lookups for hours and weather for minutes,
+        /// random endpoints, bans and recommendations are never cached
+        fn default() -> Self {
+            const HOUR: u64 = 60 * 60;
+            const DAY: u64 = 24 * HOUR;
+
+            Self::new(None)
+                .endpoint("/lyrics", Some(Duration::from_secs(3 * DAY)))
+                .endpoint("/images/tags", Some(Duration::from_secs(DAY)))
+                .endpoint("/images/image", Some(Duration::from_secs(7 * DAY)))
+                .endpoint("/kumo/geoip", Some(Duration::from_secs(DAY)))
+                .endpoint("/kumo/gis", Some(Duration::from_secs(DAY)))
+                .endpoint("/kumo/currency", Some(Duration::from_secs(HOUR)))
+                .endpoint("/kumo/weather", Some(Duration::from_secs(10 * 60)))
+        }
+    }
+
+    /// Backend and policy used by the clients to cache responses
+    pub(crate) struct ResponseCache {
+        pub(crate) backend: Box<dyn CacheBackend>,
+        pub(crate) policy: CachePolicy
+    }
+
+    impl ResponseCache {
+        pub(crate) fn new(backend: impl CacheBackend, policy: CachePolicy) -> Self {
+            Self {
+                backend: Box::new(backend),
+                policy
+            }
+        }
+
+        /// Gets the cache key and time to live of a request, None if it must not be cached
+        pub(crate) fn entry(&self, request: &Request) -> Option<(String, Duration)> {
+            if request.method() != Method::GET { return None }
+
+            let ttl = self.policy.ttl_for(request.url().path())?;
+            Some((request.url().to_string(), ttl))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{CacheBackend, FileBackend};
+        use std::{
+            convert::TryInto,
+            path::PathBuf,
+            time::{Duration, SystemTime, UNIX_EPOCH}
+        };
+
+        /// Backend in its own temporary directory, removed once the test ends
+        struct TempBackend {
+            backend: FileBackend,
+            dir: PathBuf
+        }
+
+        impl TempBackend {
+            fn new(name: &str) -> Self {
+                let dir = std::env::temp_dir().join(format!("ksoft-cache-{}-{}", name, std::process::id()));
+                let _ = std::fs::remove_dir_all(&dir);
+
+                Self {
+                    backend: FileBackend::new(&dir).unwrap(),
+                    dir
+                }
+            }
+
+            fn write(&self, key: &str, data: &[u8]) {
+                std::fs::write(self.backend.path(key), data).unwrap();
+            }
+        }
+
+        impl Drop for TempBackend {
+            fn drop(&mut self) {
+                let _ = std::fs::remove_dir_all(&self.dir);
+            }
+        }
+
+        fn now() -> u64 {
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+        }
+
+        fn entry(expires_at: u64, key: &str, body: &[u8]) -> Vec<u8> {
+            let mut data = expires_at.to_be_bytes().to_vec();
+            data.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            data.extend_from_slice(key.as_bytes());
+            data.extend_from_slice(body);
+            data
+        }
+
+        fn block_on<F: std::future::Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+        }
+
+        #[test]
+        fn round_trip() {
+            let temp = TempBackend::new("round-trip");
+            let key = "https://api.ksoft.si/lyrics/search?q=despacito";
+
+            block_on(async {
+                temp.backend.set(key, b"{\"total\": 1}".to_vec(), Duration::from_secs(60)).await;
+                assert_eq!(temp.backend.get(key).await, Some(b"{\"total\": 1}".to_vec()));
+                assert_eq!(temp.backend.get("https://api.ksoft.si/lyrics/search?q=other").await, None);
+            });
+
+            let data = std::fs::read(temp.backend.path(key)).unwrap();
+            let expires_at = u64::from_be_bytes(data[..8].try_into().unwrap());
+            assert!((now() + 59..=now() + 60).contains(&expires_at), "{}", expires_at);
+            assert_eq!(data, entry(expires_at, key, b"{\"total\": 1}"));
+            assert_eq!(std::fs::read_dir(&temp.dir).unwrap().count(), 1);
+
+            block_on(temp.backend.remove(key));
+            assert!(!temp.backend.path(key).exists());
+        }
+
+        #[test]
+        fn expired_entries() {
+            let temp = TempBackend::new("expired");
+            temp.write("expired", &entry(now() - 1, "expired", b"old"));
+            temp.write("empty", &entry(now() + 60, "empty", b""));
+
+            block_on(async {
+                assert_eq!(temp.backend.get("expired").await, None);
+                assert_eq!(temp.backend.get("empty").await, Some(Vec::new()));
+            });
+            assert!(!temp.backend.path("expired").exists());
+        }
+
+        #[test]
+        fn corrupt_files() {
+            let temp = TempBackend::new("corrupt");
+            let valid = entry(now() + 60, "key", b"body");
+            let mut huge_key = valid.clone();
+            huge_key[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+
+            for data in [&[][..], &valid[..5], &valid[..10], &valid[..14], &huge_key[..]] {
+                temp.write("key", data);
+                assert_eq!(block_on(temp.backend.get("key")), None, "{:?}", data);
+            }
+        }
+
+        #[test]
+        fn hash_collisions() {
+            let temp = TempBackend::new("collisions");
+
+            // Another key stored in the file of this one, as if both had the same hash
+            temp.write("requested", &entry(now() + 60, "stored", b"body"));
+            temp.write("prefix", &entry(now() + 60, "prefixed", b"body"));
+
+            block_on(async {
+                assert_eq!(temp.backend.get("requested").await, None);
+                assert_eq!(temp.backend.get("prefix").await, None);
+
+                temp.backend.set("requested", b"new".to_vec(), Duration::from_secs(60)).await;
+                assert_eq!(temp.backend.get("requested").await, Some(b"new".to_vec()));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let mut cache = LruCache::new(3, Duration::from_secs(60));
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        cache.insert("d", 4);
+        assert_eq!(cache.get(&"b"), None);
+
+        cache.insert("c", 30);
+        cache.insert("e", 5);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"c"), Some(30));
+        assert_eq!(cache.get(&"d"), Some(4));
+        assert_eq!(cache.get(&"e"), Some(5));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn expired_entries_are_misses() {
+        let mut cache = LruCache::new(3, Duration::ZERO);
+        cache.insert("expired", 1);
+        cache.insert_with_ttl("fresh", 2, Duration::from_secs(60));
+        cache.insert_with_ttl("forever", 3, Duration::MAX);
+
+        assert_eq!(cache.get(&"expired"), None);
+        assert_eq!(cache.get(&"fresh"), Some(2));
+        assert_eq!(cache.get(&"forever"), Some(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evictions: 0, entries: 2 });
+    }
+
+    #[test]
+    fn stats_counters() {
+        let mut cache = LruCache::new(1, Duration::from_secs(60));
+        assert_eq!(cache.stats(), CacheStats::default());
+        assert_eq!(cache.stats().hit_ratio(), 0.0);
+
+        cache.insert(1, "one");
+        cache.get(&1);
+        cache.get(&1);
+        cache.get(&2);
+        cache.insert(2, "two");
+        assert_eq!(cache.remove(&1), None);
+
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evictions: 1, entries: 1 });
+        assert!((cache.stats().hit_ratio() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evictions: 1, entries: 0 });
+    }
+
+    #[cfg(feature = "default")]
+    #[test]
+    fn policy_longest_prefix() {
+        let policy = CachePolicy::new(Some(Duration::from_secs(1)))
+            .endpoint("/images", None)
+            .endpoint("/images/tags", Some(Duration::from_secs(2)))
+            .endpoint("/images/tags/dog", Some(Duration::from_secs(3)))
+            .endpoint("/images/image", Some(Duration::from_secs(4)));
+
+        assert_eq!(policy.ttl_for("/images/tags/dog"), Some(Duration::from_secs(3)));
+        assert_eq!(policy.ttl_for("/images/tags/cat"), Some(Duration::from_secs(2)));
+        assert_eq!(policy.ttl_for("/images/tags"), Some(Duration::from_secs(2)));
+        assert_eq!(policy.ttl_for("/images/image/i-8ta8p52f-27"), Some(Duration::from_secs(4)));
+        assert_eq!(policy.ttl_for("/images/random-meme"), None);
+        assert_eq!(policy.ttl_for("/lyrics/search"), Some(Duration::from_secs(1)));
+
+        let policy = policy.endpoint("/images/tags", None);
+        assert_eq!(policy.ttl_for("/images/tags/cat"), None);
+    }
+
+    #[cfg(feature = "default")]
+    #[test]
+    fn default_policy() {
+        let policy = CachePolicy::default();
+
+        assert_eq!(policy.ttl_for("/lyrics/search"), Some(Duration::from_secs(3 * 24 * 60 * 60)));
+        assert_eq!(policy.ttl_for("/images/tags/dog"), Some(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(policy.ttl_for("/images/image/i-8ta8p52f-27"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(policy.ttl_for("/kumo/weather/currently"), Some(Duration::from_secs(10 * 60)));
+        assert_eq!(policy.ttl_for("/images/random-meme"), None);
+        assert_eq!(policy.ttl_for("/bans/check"), None);
+        assert_eq!(policy.ttl_for("/music/recommendations"), None);
+    }
+
+    #[cfg(feature = "default")]
+    #[test]
+    fn only_get_requests_are_cached() {
+        let cache = ResponseCache::new(MemoryBackend::new(1), CachePolicy::default());
+        let http = reqwest::Client::new();
+
+        let get = http.get("https://api.ksoft.si/lyrics/search?q=despacito").build().unwrap();
+        assert_eq!(cache.entry(&get), Some((
+            "https://api.ksoft.si/lyrics/search?q=despacito".to_string(),
+            Duration::from_secs(3 * 24 * 60 * 60)
+        )));
+
+        let post = http.post("https://api.ksoft.si/lyrics/search").build().unwrap();
+        assert_eq!(cache.entry(&post), None);
+
+        let random = http.get("https://api.ksoft.si/images/random-meme").build().unwrap();
+        assert_eq!(cache.entry(&random), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Library-agnostic Discord embeds for common KSoft responses
+//!
+//! Every response that makes sense in a Discord message can be converted into an [`Embed`](Embed),
+//! which can then be converted into the embed type of the Discord library in use
+//! (enable the `serenity` or `twilight` features for that)
+use crate::model::{
+    bans::BanInfoResponse,
+    images::{Image, RedditImage, WikiHowImage},
+    kumo::GeoIPResponseData,
+    music::LyricsData
+};
+
+pub const TITLE_LIMIT: usize = 256;
+pub const DESCRIPTION_LIMIT: usize = 4096;
+pub const FIELD_NAME_LIMIT: usize = 256;
+pub const FIELD_VALUE_LIMIT: usize = 1024;
+pub const FIELD_COUNT_LIMIT: usize = 25;
+pub const FOOTER_LIMIT: usize = 2048;
+pub const AUTHOR_LIMIT: usize = 256;
+
+const IMAGE_COLOR: u32 = 0x3498DB;
+const REDDIT_COLOR: u32 = 0xFF4500;
+const WIKIHOW_COLOR: u32 = 0x93B874;
+const LYRICS_COLOR: u32 = 0x1DB954;
+const GEOIP_COLOR: u32 = 0x95A5A6;
+const BAN_COLOR: u32 = 0xE74C3C;
+
+#[derive(Clone, Debug, Default)]
+pub struct Embed {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<u32>,
+    pub author: Option<String>,
+    pub thumbnail: Option<String>,
+    pub image: Option<String>,
+    pub fields: Vec<EmbedField>,
+    pub footer: Option<String>,
+    /// ISO 8601 timestamp
+    pub timestamp: Option<String>
+}
+
+#[derive(Clone, Debug)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool
+}
+
+impl Embed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(mut self, title: impl AsRef<str>) -> Self {
+        self.title = Some(truncate(title, TITLE_LIMIT));
+        self
+    }
+
+    pub fn url(mut self, url: impl ToString) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: impl AsRef<str>) -> Self {
+        self.description = Some(truncate(description, DESCRIPTION_LIMIT));
+        self
+    }
+
+    pub fn color(mut self, color: u32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn author(mut self, author: impl AsRef<str>) -> Self {
+        self.author = Some(truncate(author, AUTHOR_LIMIT));
+        self
+    }
+
+    pub fn thumbnail(mut self, url: impl ToString) -> Self {
+        self.thumbnail = Some(url.to_string());
+        self
+    }
+
+    pub fn image(mut self, url: impl ToString) -> Self {
+        self.image = Some(url.to_string());
+        self
+    }
+
+    /// Adds a field, fields over the limit of 25 and empty fields are ignored
+    pub fn field(mut self, name: impl AsRef<str>, value: impl AsRef<str>, inline: bool) -> Self {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        if self.fields.len() < FIELD_COUNT_LIMIT && !name.is_empty() && !value.is_empty() {
+            self.fields.push(EmbedField {
+                name: truncate(name, FIELD_NAME_LIMIT),
+                value: truncate(value, FIELD_VALUE_LIMIT),
+                inline
+            });
+        }
+        self
+    }
+
+    pub fn footer(mut self, footer: impl AsRef<str>) -> Self {
+        self.footer = Some(truncate(footer, FOOTER_LIMIT));
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: impl ToString) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+}
+
+/// Truncates a string to a maximum number of characters, ending it with `…` if it was truncated
+pub fn truncate(text: impl AsRef<str>, max: usize) -> String {
+    let text = text.as_ref();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut truncated = text.chars().take(max.saturating_sub(1)).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+impl From<&Image> for Embed {
+    fn from(image: &Image) -> Self {
+        Embed::new()
+            .title(&image.tag)
+            .url(&image.url)
+            .image(&image.url)
+            .color(IMAGE_COLOR)
+            .footer(format!("Snowflake: {}", image.snowflake))
+    }
+}
+
+impl From<&RedditImage> for Embed {
+    fn from(image: &RedditImage) -> Self {
+        Embed::new()
+            .title(&image.title)
+            .url(&image.source)
+            .image(&image.image_url)
+            .color(REDDIT_COLOR)
+            .author(format!("{} • u/{}", image.subreddit, image.author))
+            .field("Upvotes", image.upvotes.to_string(), true)
+            .field("Downvotes", image.downvotes.to_string(), true)
+            .field("Comments", image.comments.to_string(), true)
+            .footer(format!("Awards: {}", image.awards))
+    }
+}
+
+impl From<&WikiHowImage> for Embed {
+    fn from(image: &WikiHowImage) -> Self {
+        Embed::new()
+            .title(&image.title)
+            .url(&image.article_url)
+            .image(&image.url)
+            .color(WIKIHOW_COLOR)
+    }
+}
+
+impl From<&LyricsData> for Embed {
+    fn from(lyrics: &LyricsData) -> Self {
+        Embed::new()
+            .title(&lyrics.name)
+            .url(&lyrics.url)
+            .author(&lyrics.artist)
+            .description(&lyrics.lyrics)
+            .thumbnail(&lyrics.album_art)
+            .color(LYRICS_COLOR)
+            .footer(format!("{} ({})", lyrics.album, lyrics.album_year))
+    }
+}
+
+impl From<&GeoIPResponseData> for Embed {
+    fn from(data: &GeoIPResponseData) -> Self {
+        let title = match &data.city {
+            Some(city) => format!("{}, {}", city, data.country_name),
+            None => data.country_name.clone()
+        };
+
+        let embed = match &data.apis.open_street_map {
+            Some(map) => Embed::new().url(map),
+            None => Embed::new()
+        };
+
+        embed
+            .title(title)
+            .color(GEOIP_COLOR)
+            .field("Country", format!("{} ({})", data.country_name, data.country_code), true)
+            .field("Region", data.region.as_deref().unwrap_or_default(), true)
+            .field("Continent", &data.continent_name, true)
+            .field("Coordinates", format!("{}, {}", data.latitude, data.longitude), true)
+            .field("Time zone", &data.time_zone, true)
+            .field("Postal code", data.postal_code.as_deref().unwrap_or_default(), true)
+    }
+}
+
+impl From<&BanInfoResponse> for Embed {
+    fn from(info: &BanInfoResponse) -> Self {
+        let mut embed = Embed::new()
+            .title(format!("{}#{}", info.name, info.discriminator))
+            .description(&info.reason)
+            .color(BAN_COLOR)
+            .field("User ID", &info.id, true)
+            .field("Moderator ID", &info.moderator_id, true)
+            .field("Active", info.is_ban_active.to_string(), true)
+            .field("Appealable", info.can_be_appealed.to_string(), true)
+            .field("Proof", &info.proof, false)
+            .footer("KSoft.Si bans")
+            .timestamp(&info.timestamp);
+
+        if let Some(reason) = &info.appeal_reason {
+            embed = embed.field("Appeal reason", reason, true);
+        }
+
+        embed
+    }
+}
+
+#[cfg(feature = "serenity")]
+impl From<Embed> for serenity::builder::CreateEmbed {
+    fn from(embed: Embed) -> Self {
+        use serenity::{
+            builder::{CreateEmbedAuthor, CreateEmbedFooter},
+            model::Timestamp
+        };
+
+        let mut builder = serenity::builder::CreateEmbed::new()
+            .fields(embed.fields.into_iter().map(|f| (f.name, f.value, f.inline)));
+
+        if let Some(title) = embed.title { builder = builder.title(title); }
+        if let Some(url) = embed.url { builder = builder.url(url); }
+        if let Some(description) = embed.description { builder = builder.description(description); }
+        if let Some(color) = embed.color { builder = builder.colour(color); }
+        if let Some(author) = embed.author { builder = builder.author(CreateEmbedAuthor::new(author)); }
+        if let Some(thumbnail) = embed.thumbnail { builder = builder.thumbnail(thumbnail); }
+        if let Some(image) = embed.image { builder = builder.image(image); }
+        if let Some(footer) = embed.footer { builder = builder.footer(CreateEmbedFooter::new(footer)); }
+        if let Some(timestamp) = embed.timestamp.and_then(|t| Timestamp::parse(&t).ok()) {
+            builder = builder.timestamp(timestamp);
+        }
+
+        builder
+    }
+}
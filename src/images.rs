@@ -1,24 +1,79 @@
-use reqwest::{Client as HttpClient};
+use reqwest::{Client as HttpClient, header::CONTENT_TYPE};
+use futures_util::stream::{self, StreamExt};
 use crate::{
     make_request,
+    send_request,
     endpoint,
+    endpoint_segments,
     model::*,
-    HttpResult
+    HttpResult,
+    ApiResponse,
+    DownloadError
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant}
 };
 use crate::model::images::*;
+use crate::cache::{CacheBackend, CachePolicy, ResponseCache};
 use crate::prelude::*;
 
+/// Time after which the cached tag registry is fetched again
+pub const DEFAULT_TAGS_REFRESH: Duration = Duration::from_secs(60 * 60);
+
+/// Max number of image lookups running at the same time by default on batch fetches
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+type CachedTags = Arc<RwLock<Option<(Instant, Arc<TagRegistry>)>>>;
+
+#[derive(Clone)]
 pub struct Images {
-    http: HttpClient
+    http: HttpClient,
+    download: HttpClient,
+    tags: CachedTags,
+    tags_refresh: Duration,
+    pub(crate) response_cache: Option<Arc<ResponseCache>>
 }
 
 impl Images {
     pub fn new(http_client: HttpClient) -> Self {
+        Self::with_download_client(http_client, download_client())
+    }
+
+    /// Creates the images client downloading images with the given client, it must not send the
+    /// KSoft token, as images are hosted by third parties
+    pub fn with_download_client(http_client: HttpClient, download_client: HttpClient) -> Self {
         Self {
-            http: http_client
+            http: http_client,
+            download: download_client,
+            tags: Arc::new(RwLock::new(None)),
+            tags_refresh: DEFAULT_TAGS_REFRESH,
+            response_cache: None
         }
     }
 
+    /// Caches the responses of the images endpoints, as [`Client::with_cache`](crate::Client::with_cache)
+    /// does for every endpoint, for images clients created on their own
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use ksoft::cache::{CachePolicy, MemoryBackend};
+    ///
+    /// let images = Images::new(http_client).with_cache(MemoryBackend::new(1000), CachePolicy::default());
+    /// ```
+    pub fn with_cache(mut self, backend: impl CacheBackend, policy: CachePolicy) -> Self {
+        self.response_cache = Some(Arc::new(ResponseCache::new(backend, policy)));
+        self
+    }
+
+    /// Sets how often the cached tag registry is fetched again
+    pub fn set_tags_refresh(&mut self, interval: Duration) {
+        self.tags_refresh = interval;
+    }
+
     ///Gets a random image based in a given tag
     ///
     /// # Example
@@ -42,7 +97,7 @@ impl Images {
             .query(&[("nsfw", nsfw)]);
 
 
-        make_request::<Image, ImageError>(builder).await
+        make_request::<Image, ImageError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Gets a random meme from reddit
@@ -55,9 +110,9 @@ impl Images {
     /// }
     /// ```
     pub async fn random_meme(&self) -> reqwest::Result<RedditImage>{
-        let response = self.http.get(endpoint("/images/random-meme").as_str())
-            .send()
-            .await?;
+        let builder = self.http.get(endpoint("/images/random-meme").as_str());
+
+        let response = send_request(builder, self.response_cache.as_deref()).await?;
 
         let image = response.json::<RedditImage>().await?;
         Ok(image)
@@ -73,8 +128,9 @@ impl Images {
     /// }
     /// ```
     pub async fn random_aww(&self) -> reqwest::Result<RedditImage>{
-        return self.http.get(endpoint("/images/random-aww").as_str())
-            .send()
+        let builder = self.http.get(endpoint("/images/random-aww").as_str());
+
+        send_request(builder, self.response_cache.as_deref())
             .await?
             .json::<RedditImage>()
             .await
@@ -82,10 +138,14 @@ impl Images {
 
     ///Gets a random post from a given subreddit
     ///
+    /// The name is validated beforehand with [`Subreddit::new`](crate::model::images::Subreddit::new),
+    /// which also accepts `r/memes` and `/r/memes`
+    ///
     /// # Example
     ///
     /// ```rust,ignore
-    /// if let Ok(res) = client.images.random_reddit("Technology", true, SpanType::Day).await {
+    /// let subreddit: Subreddit = "Technology".parse()?;
+    /// if let Ok(res) = client.images.random_reddit(&subreddit, true, SpanType::Day).await {
     ///     match res {
     ///         Ok(red) => {
     ///             //do something with the reddit image
@@ -96,14 +156,46 @@ impl Images {
     ///     }
     /// }
     /// ```
-    pub async fn random_reddit(&self, subreddit: impl ToString, remove_nsfw: bool, span: SpanType) -> HttpResult<RedditImage, ImageError>{
-        if subreddit.to_string().is_empty() { panic!("You have to specify a subreddit to search in") }
-
-        let builder = self.http.get(endpoint(format!("/images/rand-reddit/{}", subreddit.to_string())).as_str())
+    pub async fn random_reddit(&self, subreddit: &Subreddit, remove_nsfw: bool, span: SpanType) -> HttpResult<RedditImage, ImageError>{
+        let builder = self.http.get(endpoint_segments("/images/rand-reddit", &[subreddit.name()]).as_str())
             .query(&[("remove_nsfw", remove_nsfw)])
             .query(&[("span", span.to_string())]);
 
-        make_request::<RedditImage, ImageError>(builder).await
+        make_request::<RedditImage, ImageError>(builder, self.response_cache.as_deref()).await
+    }
+
+    ///Gets a random post from a subreddit matching the query filters
+    ///
+    /// Posts are requested until one matches or the query retries run out, returning None in that case
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = RedditQuery::new("aww".parse()?).min_upvotes(1000).media_type(MediaType::Gif);
+    /// if let Ok(res) = client.images.reddit(&query).await {
+    ///     match res {
+    ///         Ok(Some(red)) => {
+    ///             //do something with the reddit image
+    ///         },
+    ///         Ok(None) => {
+    ///             //no post matched the filters
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <ImageError> struct
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn reddit(&self, query: &RedditQuery) -> HttpResult<Option<RedditImage>, ImageError> {
+        for _ in 0..=query.retries {
+            match self.random_reddit(&query.subreddit, query.remove_nsfw, query.span).await? {
+                Ok(image) if query.matches(&image) => return Ok(Ok(Some(image))),
+                Ok(_) => continue,
+                Err(why) => return Ok(Err(why))
+            }
+        }
+
+        Ok(Ok(None))
     }
 
     ///Gets a random WikiHow image
@@ -116,9 +208,10 @@ impl Images {
     /// }
     /// ```
     pub async fn random_wikihow(&self, nsfw: bool) -> reqwest::Result<WikiHowImage> {
-        return self.http.get(endpoint("/images/random-wikihow").as_str())
-            .query(&[("nsfw", nsfw)])
-            .send()
+        let builder = self.http.get(endpoint("/images/random-wikihow").as_str())
+            .query(&[("nsfw", nsfw)]);
+
+        send_request(builder, self.response_cache.as_deref())
             .await?
             .json::<WikiHowImage>()
             .await
@@ -133,8 +226,9 @@ impl Images {
     /// }
     /// ```
     pub async fn get_tags(&self) -> reqwest::Result<TagList> {
-        return self.http.get(endpoint("/images/tags").as_str())
-            .send()
+        let builder = self.http.get(endpoint("/images/tags").as_str());
+
+        send_request(builder, self.response_cache.as_deref())
             .await?
             .json::<TagList>()
             .await
@@ -159,9 +253,59 @@ impl Images {
     pub async fn get_image(&self, sf: impl ToString) -> HttpResult<Image, ImageError> {
         if sf.to_string().is_empty() { panic!("Snowflake id cannot be empty") }
 
-        let builder = self.http.get(endpoint(format!("/images/image/{}", sf.to_string())).as_str());
+        let builder = self.http.get(endpoint_segments("/images/image", &[&sf.to_string()]).as_str());
 
-        make_request::<Image, ImageError>(builder).await
+        make_request::<Image, ImageError>(builder, self.response_cache.as_deref()).await
+    }
+
+    ///Gets multiple images by their Snowflakes, running up to `concurrency` lookups at the same time
+    ///
+    /// Results are returned in the same order as the given Snowflakes, empty Snowflakes get an
+    /// [`ImageLookupError::EmptySnowflake`] without being sent
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let results = client.images.advanced_get_images(saved_snowflakes, 16).await;
+    /// for res in results {
+    ///     if let Ok(Ok(img)) = res {
+    ///         //do something with the image
+    ///     }
+    /// }
+    /// ```
+    pub async fn advanced_get_images<I>(&self, snowflakes: I, concurrency: usize) -> Vec<HttpResult<Image, ImageLookupError>>
+    where
+        I: IntoIterator,
+        I::Item: ToString
+    {
+        stream::iter(snowflakes)
+            .map(|sf| async move {
+                let sf = sf.to_string();
+                // Rejected here instead of panicking in get_image, so one bad item does not lose the whole batch
+                if sf.is_empty() {
+                    return Ok(Err(ImageLookupError::EmptySnowflake));
+                }
+
+                Ok(self.get_image(sf).await?.map_err(ImageLookupError::from))
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    ///Shortcut to advanced_get_images() but with default concurrency
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let results = client.images.get_images(vec!["i-8ta8p52f-27", "i-e7h3c4ho-51"]).await;
+    /// ```
+    pub async fn get_images<I>(&self, snowflakes: I) -> Vec<HttpResult<Image, ImageLookupError>>
+    where
+        I: IntoIterator,
+        I::Item: ToString
+    {
+        self.advanced_get_images(snowflakes, DEFAULT_BATCH_CONCURRENCY).await
     }
 
     ///Get a tag using its name
@@ -176,11 +320,33 @@ impl Images {
     pub async fn get_tag(&self, tag: impl ToString) -> reqwest::Result<TagList> {
         if tag.to_string().is_empty() { panic!("Tag cannot be empty") }
 
-        let response = self.http.get(endpoint(format!("/images/tags/{}", tag.to_string())).as_str())
-            .send()
-            .await?;
+        let builder = self.http.get(endpoint_segments("/images/tags", &[&tag.to_string()]).as_str());
+
+        send_request(builder, self.response_cache.as_deref())
+            .await?
+            .json::<TagList>()
+            .await
+    }
 
-        response.json::<TagList>().await
+    ///Searches tags by a partial name, best matches first, using the same lookup as [`get_tag`](Self::get_tag)
+    ///
+    /// An empty query lists every tag from [`get_tags`](Self::get_tags) instead
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(tags) = client.images.search_tags("dog", false).await {
+    ///     //offer the tags as autocomplete choices
+    /// }
+    /// ```
+    pub async fn search_tags(&self, query: impl ToString, nsfw: bool) -> reqwest::Result<Vec<TagModel>> {
+        let query = query.to_string();
+        let list = match query.trim() {
+            "" => self.get_tags().await?,
+            query => self.get_tag(query).await?
+        };
+
+        Ok(list.ranked(query, nsfw))
     }
 
     ///Get a random NSFW image
@@ -193,11 +359,324 @@ impl Images {
     /// }
     /// ```
     pub async fn random_nsfw(&self, gifs: bool) -> reqwest::Result<RedditImage> {
-        let response = self.http.get(endpoint("/images/random-nsfw").as_str())
-            .query(&[("gifs", gifs)])
+        let builder = self.http.get(endpoint("/images/random-nsfw").as_str())
+            .query(&[("gifs", gifs)]);
+
+        let response = send_request(builder, self.response_cache.as_deref()).await?;
+
+        response.json::<RedditImage>().await
+    }
+
+    ///Downloads an image, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download(&image).await {
+    ///     //upload file.bytes as an attachment named format!("image.{}", file.extension)
+    /// }
+    /// ```
+    pub async fn download(&self, image: &Image) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.url, DEFAULT_DOWNLOAD_LIMIT).await
+    }
+
+    ///Downloads the image of a reddit post, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_reddit(&meme).await {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub async fn download_reddit(&self, image: &RedditImage) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.image_url, DEFAULT_DOWNLOAD_LIMIT).await
+    }
+
+    ///Downloads a WikiHow image, detecting its mime type and extension
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_wikihow(&wiki_image).await {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub async fn download_wikihow(&self, image: &WikiHowImage) -> Result<ImageDownload, DownloadError> {
+        self.download_url(&image.url, DEFAULT_DOWNLOAD_LIMIT).await
+    }
+
+    ///Downloads a file from a given url, failing if it is larger than `limit` bytes
+    ///
+    /// The request is sent by a separate http client without the KSoft authorization header,
+    /// so the token is never leaked to third party hosts
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(file) = client.images.download_url(&meme.image_url, 25 * 1024 * 1024).await {
+    ///     //do something with the file
+    /// }
+    /// ```
+    pub async fn download_url(&self, url: impl AsRef<str>, limit: usize) -> Result<ImageDownload, DownloadError> {
+        let mut response = self.download.get(url.as_ref()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(DownloadError::UnsuccessfulResponse(response.status().as_u16()));
+        }
+        if response.content_length().is_some_and(|len| len > limit as u64) {
+            return Err(DownloadError::TooLarge(limit));
+        }
+
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .map(ToString::to_string);
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > limit {
+                return Err(DownloadError::TooLarge(limit));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(ImageDownload::new(bytes, content_type.as_deref()))
+    }
+
+    ///Gets the registry of available tags, it is cached and fetched again once it gets older than the refresh interval
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(registry) = client.images.tag_registry().await {
+    ///     let suggestions = registry.suggest("dgoe", false);
+    /// }
+    /// ```
+    pub async fn tag_registry(&self) -> reqwest::Result<Arc<TagRegistry>> {
+        if let Some((fetched_at, registry)) = &*self.tags.read().expect("Tag registry lock poisoned") {
+            if fetched_at.elapsed() < self.tags_refresh {
+                return Ok(Arc::clone(registry));
+            }
+        }
+
+        // Skips the response cache, the registry already keeps the tags for the refresh interval
+        let tags = self.http.get(endpoint("/images/tags").as_str())
             .send()
+            .await?
+            .json::<TagList>()
             .await?;
 
-        response.json::<RedditImage>().await
+        let registry = Arc::new(TagRegistry::from(tags));
+        *self.tags.write().expect("Tag registry lock poisoned") = Some((Instant::now(), Arc::clone(&registry)));
+
+        Ok(registry)
+    }
+
+    ///Checks a tag locally against the cached tag registry
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(res) = client.images.tag("doge", false).await {
+    ///     match res {
+    ///         Ok(tag) => {
+    ///             let image = client.images.random_image(tag, false).await;
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <TagError>, it may contain suggestions
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn tag(&self, tag: impl AsRef<str>, nsfw: bool) -> reqwest::Result<ApiResponse<Tag, TagError>> {
+        Ok(self.tag_registry().await?.check(tag, nsfw))
+    }
+}
+
+/// Random images served per key remembered by default
+pub const DEFAULT_SAMPLER_WINDOW: usize = 20;
+/// Extra requests made by default to get an image that has not been served recently
+pub const DEFAULT_SAMPLER_RETRIES: usize = 3;
+
+/// Wrapper around [`Images`](Images) that avoids serving the same random image twice in a row
+///
+/// Served images are remembered per key (a channel id for example), if an image was recently
+/// served for that key, another one is requested, up to the configured number of retries
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let sampler = DedupSampler::new(client.images.clone())
+///     .window(50)
+///     .retries(5);
+///
+/// if let Ok(meme) = sampler.random_meme(channel_id).await {
+///     //do something with the meme
+/// }
+/// ```
+pub struct DedupSampler {
+    images: Images,
+    history: Mutex<HashMap<String, VecDeque<String>>>,
+    window: usize,
+    retries: usize
+}
+
+impl DedupSampler {
+    pub fn new(images: Images) -> Self {
+        Self {
+            images,
+            history: Mutex::new(HashMap::new()),
+            window: DEFAULT_SAMPLER_WINDOW,
+            retries: DEFAULT_SAMPLER_RETRIES
+        }
+    }
+
+    /// Sets how many served images are remembered per key
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Sets how many extra requests can be made to get an unseen image
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Forgets the images served for a key
+    pub fn clear(&self, key: impl ToString) {
+        self.history.lock().expect("Sampler history lock poisoned").remove(&key.to_string());
+    }
+
+    ///Gets a random image based in a given tag that was not recently served for the key
+    pub async fn random_image(&self, key: impl ToString, tag: impl ToString, nsfw: bool) -> HttpResult<Image, ImageError> {
+        let tag = tag.to_string();
+        self.sample(key.to_string(), || self.images.random_image(&tag, nsfw), |res: &ApiResponse<Image, ImageError>| {
+            res.as_ref().ok().map(|image| image.snowflake.clone())
+        }).await
+    }
+
+    ///Gets a random meme that was not recently served for the key
+    pub async fn random_meme(&self, key: impl ToString) -> reqwest::Result<RedditImage> {
+        self.sample(key.to_string(), || self.images.random_meme(), |image: &RedditImage| Some(image.source.clone())).await
+    }
+
+    ///Gets a random cute image that was not recently served for the key
+    pub async fn random_aww(&self, key: impl ToString) -> reqwest::Result<RedditImage> {
+        self.sample(key.to_string(), || self.images.random_aww(), |image: &RedditImage| Some(image.source.clone())).await
+    }
+
+    /// Fetches until an unseen item is found or retries run out, items without id are returned right away
+    async fn sample<T, E, F, Fut>(&self, key: String, fetch: F, id_of: impl Fn(&T) -> Option<String>) -> Result<T, E>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>
+    {
+        let mut attempts = 0;
+        loop {
+            let item = fetch().await?;
+            let id = match id_of(&item) {
+                Some(id) => id,
+                None => return Ok(item)
+            };
+
+            let mut history = self.history.lock().expect("Sampler history lock poisoned");
+            let served = history.entry(key.clone()).or_default();
+            if !served.contains(&id) || attempts >= self.retries {
+                served.push_back(id);
+                while served.len() > self.window {
+                    served.pop_front();
+                }
+                return Ok(item);
+            }
+
+            attempts += 1;
+        }
+    }
+}
+
+/// Client without the KSoft authorization header, used to download images from third party hosts
+pub(crate) fn download_client() -> HttpClient {
+    HttpClient::builder()
+        .user_agent("KSoft.rs")
+        .build()
+        .expect("Something went wrong when creating download client")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cache::{CacheBackend, CachePolicy, MemoryBackend},
+        images::Images,
+        model::images::ImageLookupError,
+        Client
+    };
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::Arc,
+        thread,
+        time::Duration
+    };
+
+    /// Serves a single image, returning the request it received
+    fn image_host() -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                if read == 0 { break }
+                request.extend_from_slice(&buf[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\n\x89PNG").unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn downloads_do_not_send_token() {
+        let (url, server) = image_host();
+        let client = Client::new("secret-token");
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+        let download = runtime.block_on(client.images.download_url(&url, 1024)).unwrap();
+        let request = server.join().unwrap();
+
+        assert_eq!(download.bytes, b"\x89PNG");
+        assert!(request.starts_with("get /image.png"), "{}", request);
+        assert!(!request.contains("authorization"), "{}", request);
+        assert!(!request.contains("secret-token"), "{}", request);
+    }
+
+    #[test]
+    fn empty_snowflakes_are_not_sent() {
+        let client = Client::new("secret-token");
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+        let results = runtime.block_on(client.images.get_images(vec!["", ""]));
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|res| matches!(res, Ok(Err(ImageLookupError::EmptySnowflake)))));
+    }
+
+    #[test]
+    fn standalone_images_cache() {
+        let backend = Arc::new(MemoryBackend::new(10));
+        let images = Images::new(reqwest::Client::new()).with_cache(Arc::clone(&backend), CachePolicy::default());
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+        runtime.block_on(async {
+            let body = br#"{"models": null, "tags": ["dog", "doge"], "nsfw_tags": []}"#.to_vec();
+            backend.set("https://api.ksoft.si/images/tags", body, Duration::from_secs(60)).await;
+
+            let tags = images.get_tags().await.unwrap();
+            assert_eq!(tags.tags, vec!["dog", "doge"]);
+        });
+        assert_eq!(backend.stats().hits, 1);
     }
 }
+
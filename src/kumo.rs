@@ -1,53 +1,111 @@
 use reqwest::{Client as HttpClient};
+use std::sync::Arc;
+use crate::cache::ResponseCache;
 use crate::{
     make_request,
+    collect_all,
     endpoint,
     model::*,
     HttpResult
 };
-use crate::model::kumo::*;
+use crate::model::{currency::*, kumo::*};
+use serde::de::DeserializeOwned;
+
+/// Currency conversions running at the same time on multi-target conversions
+pub const DEFAULT_CONVERSION_CONCURRENCY: usize = 4;
 
 pub struct Kumo {
-    http: HttpClient
+    http: HttpClient,
+    pub(crate) response_cache: Option<Arc<ResponseCache>>
 }
 
 impl Kumo {
     pub fn new(http_client: HttpClient) -> Self {
         Self {
-            http: http_client
+            http: http_client,
+            response_cache: None
         }
     }
 
-    ///Get data from a given IP address
+    ///Get data from a given IPv4 or IPv6 address, given as a `std::net` address or a string
+    ///
+    /// Invalid addresses and addresses that are not public, like private or loopback ones,
+    /// are rejected with a [`GeoIpError`](GeoIpError) without making a request
     ///
     /// # Example
     ///
     /// ```rust,ignore
-    /// if let Ok(res) = client.kumo.geoip("AmazingNonExistingIP").await {
+    /// if let Ok(res) = client.kumo.geoip("2001:4860:4860::8888").await {
     ///     match res {
     ///         Ok(ip) => {
     ///             //do something with ip info
     ///         },
     ///         Err(why) => {
-    ///             //do something with the <KumoError> struct
+    ///             //do something with the <GeoIpError> enum
     ///         }
     ///     }
     /// }
     /// ```
-    pub async fn geoip(&self, ip: impl ToString) -> HttpResult<GeoIPResponse, KumoError> {
-        let ip_parsed = ip.to_string().parse::<std::net::Ipv4Addr>().expect("Cannot parse as ip");
+    pub async fn geoip(&self, ip: impl IntoIpAddr) -> HttpResult<GeoIPResponse, GeoIpError> {
+        let ip = match ip.into_ip_addr().and_then(GeoIpError::check) {
+            Ok(ip) => ip,
+            Err(why) => return Ok(Err(why))
+        };
 
         let builder = self.http.get(endpoint("/kumo/geoip").as_str())
-            .query(&[("ip", ip_parsed.to_string())]);
+            .query(&[("ip", ip.to_string())]);
+
+        Ok(make_request::<GeoIPResponse, KumoError>(builder, self.response_cache.as_deref()).await?.map_err(GeoIpError::Api))
+    }
+
+    ///Find a location by its name or address specifying custom options
+    ///
+    /// Empty queries are rejected with a [`GisError`](GisError) without making a request
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = GisOptions::new().more(true).map_zoom(12);
+    /// if let Ok(res) = client.kumo.advanced_gis("Madrid", &options).await {
+    ///     match res {
+    ///         Ok(gis) => {
+    ///             //do something with gis.data
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <GisError> enum
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn advanced_gis(&self, query: impl AsRef<str>, options: &GisOptions) -> HttpResult<GisResponse, GisError> {
+        let query = query.as_ref().trim();
+        if query.is_empty() { return Ok(Err(GisError::EmptyQuery)) }
+
+        let builder = self.http.get(endpoint("/kumo/gis").as_str())
+            .query(&options.query(query));
+
+        Ok(make_request::<GisResponse, KumoError>(builder, self.response_cache.as_deref()).await?.map_err(GisError::Api))
+    }
 
-        make_request::<GeoIPResponse, KumoError>(builder).await
+    ///Shortcut to advanced_gis() but with default options
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(gis)) = client.kumo.gis("Madrid").await {
+    ///     //do something with gis.data
+    /// }
+    /// ```
+    pub async fn gis(&self, query: impl AsRef<str>) -> HttpResult<GisResponse, GisError> {
+        self.advanced_gis(query, &GisOptions::default()).await
     }
 
-    ///Performs currency conversion
+    ///Performs currency conversion, amounts are sent as exact decimals
     ///
     /// # Example
     /// ```rust,ignore
-    /// if let Ok(res) = client.kumo.convert_currency(120.0, "USD", "EUR").await {
+    /// let amount: Amount = "120.50".parse()?;
+    /// if let Ok(res) = client.kumo.convert_currency(amount, Currency::USD, Currency::EUR).await {
     ///     match res {
     ///         Ok(conversion) => {
     ///             //do something with conversion info
@@ -58,10 +116,125 @@ impl Kumo {
     ///     }
     /// }
     /// ```
-    pub async fn convert_currency<C: ToString>(&self, value: f64, from: C, to: C) -> HttpResult<CurrencyConversionResponse, KumoError> {
+    pub async fn convert_currency(&self, value: impl Into<Amount>, from: Currency, to: Currency) -> HttpResult<CurrencyConversionResponse, KumoError> {
         let builder = self.http.get(endpoint("/kumo/currency").as_str())
-            .query(&[("from", from.to_string()), ("to", to.to_string()), ("value", value.to_string())]);
+            .query(&[("from", from.to_string()), ("to", to.to_string()), ("value", value.into().to_string())]);
+
+        make_request::<CurrencyConversionResponse, KumoError>(builder, self.response_cache.as_deref()).await
+    }
+
+    ///Converts an amount into several currencies, running the conversions concurrently
+    ///
+    /// Conversions are returned in the same order as the target currencies, the first error
+    /// is returned instead and the conversions still pending are not sent
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let amount: Amount = "19.99".parse()?;
+    /// if let Ok(Ok(conversions)) = client.kumo.convert_currency_many(amount, Currency::USD, [Currency::EUR, Currency::GBP]).await {
+    ///     for (currency, conversion) in conversions {
+    ///         //do something with conversion info
+    ///     }
+    /// }
+    /// ```
+    pub async fn convert_currency_many<I>(&self, value: impl Into<Amount>, from: Currency, to: I) -> HttpResult<Vec<(Currency, CurrencyConversionResponse)>, KumoError>
+    where
+        I: IntoIterator<Item = Currency>
+    {
+        let value = value.into();
+        let conversions = to.into_iter().map(|currency| async move {
+            Ok(self.convert_currency(value, from, currency).await?.map(|conversion| (currency, conversion)))
+        });
+
+        collect_all(conversions, DEFAULT_CONVERSION_CONCURRENCY).await
+    }
+
+    ///Get the current weather of a location specifying custom units and language
+    ///
+    /// Empty locations are rejected with a [`WeatherError`](WeatherError) without making a request
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = WeatherOptions::new().units(Units::Us).language("es");
+    /// if let Ok(res) = client.kumo.advanced_weather("Madrid", &options).await {
+    ///     match res {
+    ///         Ok(weather) => {
+    ///             //do something with weather.data
+    ///         },
+    ///         Err(why) => {
+    ///             //do something with the <WeatherError> enum
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn advanced_weather(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.weather_request("/kumo/weather/easy", location.as_ref(), options).await
+    }
+
+    ///Shortcut to advanced_weather() but with SI units and the default language
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(weather)) = client.kumo.weather("Madrid").await {
+    ///     //do something with weather.data
+    /// }
+    /// ```
+    pub async fn weather(&self, location: impl AsRef<str>) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.advanced_weather(location, &WeatherOptions::default()).await
+    }
+
+    ///Get the current weather report of a location
+    pub async fn weather_currently(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherData>, WeatherError> {
+        self.weather_request("/kumo/weather/currently", location.as_ref(), options).await
+    }
+
+    ///Get the weather forecast of a location for the next hour, minute by minute
+    pub async fn weather_minutely(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/minutely", location.as_ref(), options).await
+    }
+
+    ///Get the weather forecast of a location for the next two days, hour by hour
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(forecast)) = client.kumo.weather_hourly("Madrid", &WeatherOptions::default()).await {
+    ///     for hour in forecast.data.data {
+    ///         //do something with the hour
+    ///     }
+    /// }
+    /// ```
+    pub async fn weather_hourly(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/hourly", location.as_ref(), options).await
+    }
+
+    ///Get the weather forecast of a location for the next week, day by day
+    pub async fn weather_daily(&self, location: impl AsRef<str>, options: &WeatherOptions) -> HttpResult<WeatherResponse<WeatherForecast>, WeatherError> {
+        self.weather_request("/kumo/weather/daily", location.as_ref(), options).await
+    }
+
+    async fn weather_request<T: DeserializeOwned>(&self, path: &str, location: &str, options: &WeatherOptions) -> HttpResult<WeatherResponse<T>, WeatherError> {
+        let location = location.trim();
+        if location.is_empty() { return Ok(Err(WeatherError::EmptyLocation)) }
+
+        let builder = self.http.get(endpoint(path).as_str())
+            .query(&options.query(location));
+
+        Ok(make_request::<WeatherResponse<T>, KumoError>(builder, self.response_cache.as_deref()).await?.map_err(WeatherError::Api))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>(_: T) {}
 
-        make_request::<CurrencyConversionResponse, KumoError>(builder).await
+    // Never polled, only checks the future can be spawned on a multi-threaded runtime
+    #[allow(dead_code)]
+    fn convert_currency_many_is_send(kumo: &Kumo) {
+        assert_send(kumo.convert_currency_many(1, Currency::USD, vec![Currency::EUR]));
     }
 }
@@ -0,0 +1,136 @@
+//! Lavalink track identifiers for recommendations
+//!
+//! Lavalink loads tracks from direct URLs or from searches such as `ytsearch:query`,
+//! the helpers here build both from recommended tracks, with a fallback chain for
+//! tracks whose YouTube video can't be loaded
+//!
+//! # Example
+//!
+//! ```rust
+//! use ksoft::{lavalink::Identifier, model::music::RecommendationTrack};
+//!
+//! let track: RecommendationTrack = serde_json::from_str(r#"{
+//!     "youtube": {
+//!         "id": "kJQP7kiw5Fk",
+//!         "link": "https://youtube.com/watch?v=kJQP7kiw5Fk",
+//!         "title": "Luis Fonsi - Despacito ft. Daddy Yankee",
+//!         "thumbnail": "https://i.ytimg.com/vi/kJQP7kiw5Fk/hqdefault.jpg",
+//!         "description": ""
+//!     },
+//!     "spotify": {
+//!         "id": "6habFhsOp2NvshLv26DqMb",
+//!         "album": { "name": "VIDA", "album_art": "", "link": "" },
+//!         "artists": [
+//!             { "name": "Luis Fonsi", "link": "" },
+//!             { "name": "Daddy Yankee", "link": "" }
+//!         ],
+//!         "name": "Despacito - Remix",
+//!         "link": "https://open.spotify.com/track/6habFhsOp2NvshLv26DqMb"
+//!     },
+//!     "name": "Luis Fonsi - Despacito"
+//! }"#).unwrap();
+//!
+//! assert_eq!(track.identifier().to_string(), "https://www.youtube.com/watch?v=kJQP7kiw5Fk");
+//! assert_eq!(track.search_query(), "Luis Fonsi, Daddy Yankee - Despacito - Remix");
+//! assert_eq!(
+//!     track.identifiers().iter().map(Identifier::to_string).collect::<Vec<_>>(),
+//!     [
+//!         "https://www.youtube.com/watch?v=kJQP7kiw5Fk",
+//!         "ytsearch:Luis Fonsi, Daddy Yankee - Despacito - Remix",
+//!         "ytsearch:Luis Fonsi - Despacito"
+//!     ]
+//! );
+//!
+//! let missing: RecommendationTrack = serde_json::from_str(r#"{
+//!     "youtube": { "id": "", "link": "", "title": "", "thumbnail": "", "description": "" },
+//!     "spotify": {
+//!         "id": "", "album": { "name": "", "album_art": "", "link": "" },
+//!         "artists": [], "name": "", "link": ""
+//!     },
+//!     "name": "Luis Fonsi - Despacito"
+//! }"#).unwrap();
+//!
+//! assert_eq!(missing.youtube.identifier(), None);
+//! assert_eq!(missing.identifier(), Identifier::Search("Luis Fonsi - Despacito".to_string()));
+//! ```
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use crate::model::music::{RecommendationTrack, YoutubeTrack};
+
+/// Prefix of YouTube searches
+pub const YOUTUBE_SEARCH: &str = "ytsearch:";
+
+/// Identifier to load a track through Lavalink
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Identifier {
+    /// Direct URL of the track
+    Url(String),
+    /// YouTube search, displayed with the `ytsearch:` prefix
+    Search(String)
+}
+
+impl Identifier {
+    pub fn is_search(&self) -> bool {
+        matches!(self, Identifier::Search(_))
+    }
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Identifier::Url(url) => write!(f, "{}", url),
+            Identifier::Search(query) => write!(f, "{}{}", YOUTUBE_SEARCH, query)
+        }
+    }
+}
+
+impl YoutubeTrack {
+    /// Direct URL of the video, None if the track has no YouTube video
+    pub fn identifier(&self) -> Option<Identifier> {
+        if !self.id.is_empty() {
+            Some(Identifier::Url(format!("https://www.youtube.com/watch?v={}", self.id)))
+        } else if !self.link.is_empty() {
+            Some(Identifier::Url(self.link.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl RecommendationTrack {
+    /// Search query in the form `Artist - Title`, using the Spotify metadata when available
+    pub fn search_query(&self) -> String {
+        let artists = self.spotify.artists.iter()
+            .map(|artist| artist.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match (artists.is_empty(), self.spotify.name.is_empty()) {
+            (false, false) => format!("{} - {}", artists, self.spotify.name),
+            (true, false) => self.spotify.name.clone(),
+            _ if !self.youtube.title.is_empty() => self.youtube.title.clone(),
+            _ => self.name.clone()
+        }
+    }
+
+    /// Best identifier of the track, the YouTube video if any, a search otherwise
+    pub fn identifier(&self) -> Identifier {
+        self.youtube.identifier()
+            .unwrap_or_else(|| Identifier::Search(self.search_query()))
+    }
+
+    /// Identifiers to try in order until Lavalink loads the track: the YouTube video,
+    /// an `Artist - Title` search and a search of the track name
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        let mut identifiers = Vec::with_capacity(3);
+        identifiers.extend(self.youtube.identifier());
+
+        for query in [self.search_query(), self.name.clone()].iter() {
+            let search = Identifier::Search(query.trim().to_string());
+            if !query.trim().is_empty() && !identifiers.contains(&search) {
+                identifiers.push(search);
+            }
+        }
+
+        identifiers
+    }
+}
@@ -1,15 +1,24 @@
+pub mod cache;
+pub mod embed;
+pub mod lyrics;
 pub mod model;
+pub mod playlist;
 pub mod prelude;
+mod utils;
 
 
 #[cfg(feature = "default")]
 use async_trait::async_trait;
 #[cfg(feature = "default")]
-use reqwest::{Client as HttpClient, RequestBuilder};
+use reqwest::{Client as HttpClient, RequestBuilder, Response};
 #[cfg(feature = "default")]
 use reqwest::header::HeaderMap;
 #[cfg(feature = "default")]
 use serde::de::DeserializeOwned;
+#[cfg(feature = "default")]
+use futures_util::stream::{FuturesOrdered, StreamExt};
+#[cfg(feature = "default")]
+use std::future::Future;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
@@ -21,13 +30,18 @@ pub mod bans;
 pub mod kumo;
 #[cfg(feature = "default")]
 pub mod music;
+#[cfg(feature = "twilight")]
+pub mod twilight;
+#[cfg(feature = "lavalink")]
+pub mod lavalink;
 #[cfg(feature = "default")]
 use crate::{
-    images::Images,
+    images::{download_client, Images},
     bans::Bans,
     kumo::Kumo,
     music::Music,
-    model::bans::BanUpdate
+    model::bans::BanUpdate,
+    cache::{CacheBackend, CachePolicy, ResponseCache}
 };
 #[cfg(feature = "serenity")]
 use typemap_rev::TypeMapKey;
@@ -53,7 +67,7 @@ pub struct Client {
 #[cfg(feature = "default")]
 impl Client {
     pub fn new(token: impl ToString) -> Self {
-        let mut default_auth_header =  HeaderMap::new();
+        let mut default_auth_header = HeaderMap::new();
         default_auth_header.insert("Authorization", format!("Bearer {}", token.to_string()).parse().expect("Cannot parse default headers"));
         let http_client = HttpClient::builder()
             .default_headers(default_auth_header)
@@ -63,7 +77,7 @@ impl Client {
 
         Self {
             token: token.to_string(),
-            images: Images::new(http_client.clone()),
+            images: Images::with_download_client(http_client.clone(), download_client()),
             bans: Bans::new(http_client.clone()),
             kumo: Kumo::new(http_client.clone()),
             music: Music::new(http_client.clone()),
@@ -94,9 +108,42 @@ impl Client {
     ///         println!("Ban update received: {:#?}", data);
     ///     }
     /// }
-    pub fn event_handler(&self, handler: impl EventHandler + Send + Sync + 'static ) {
+    pub fn event_handler(&self, handler: impl EventHandler ) {
         self.bans.event_handler(handler);
     }
+
+    /// Caches responses of every endpoint in the given backend, following the policy to decide
+    /// for how long each endpoint is cached
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use ksoft::cache::{CachePolicy, FileBackend};
+    ///
+    /// let client = Client::new(std::env::var("KSOFT_TOKEN").expect("KSoft token not found"))
+    ///     .with_cache(FileBackend::new("ksoft-cache")?, CachePolicy::default());
+    /// ```
+    ///
+    /// Pass the backend inside an `Arc` to keep a handle to it
+    ///
+    /// ```rust,ignore
+    /// use ksoft::cache::{CachePolicy, MemoryBackend};
+    ///
+    /// let backend = Arc::new(MemoryBackend::new(1000));
+    /// let client = Client::new(std::env::var("KSOFT_TOKEN").expect("KSoft token not found"))
+    ///     .with_cache(Arc::clone(&backend), CachePolicy::default());
+    ///
+    /// println!("Cache hit ratio: {}", backend.stats().hit_ratio());
+    /// ```
+    pub fn with_cache(mut self, backend: impl CacheBackend, policy: CachePolicy) -> Self {
+        let cache = std::sync::Arc::new(ResponseCache::new(backend, policy));
+
+        self.images.response_cache = Some(cache.clone());
+        self.bans.response_cache = Some(cache.clone());
+        self.kumo.response_cache = Some(cache.clone());
+        self.music.response_cache = Some(cache);
+        self
+    }
 }
 
 #[cfg(feature = "serenity")]
@@ -104,12 +151,41 @@ impl TypeMapKey for Client {
     type Value = std::sync::Arc<Self>;
 }
 
+/// Sends a request, going through the response cache if there is one. Only successful GET requests are cached
+#[cfg(feature = "default")]
+pub(crate) async fn send_request(c: RequestBuilder, cache: Option<&ResponseCache>) -> reqwest::Result<Response> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return c.send().await
+    };
+
+    let (client, request) = c.build_split();
+    let request = request?;
+    let (key, ttl) = match cache.entry(&request) {
+        Some(entry) => entry,
+        None => return client.execute(request).await
+    };
+
+    if let Some(body) = cache.backend.get(&key).await {
+        return Ok(http::Response::new(body).into());
+    }
+
+    let response = client.execute(request).await?;
+    if response.status() != reqwest::StatusCode::OK {
+        return Ok(response);
+    }
+
+    let body = response.bytes().await?;
+    cache.backend.set(&key, body.to_vec(), ttl).await;
+    Ok(http::Response::new(body).into())
+}
+
 #[cfg(feature = "default")]
-pub(crate) async fn make_request<S: DeserializeOwned, E: DeserializeOwned>(c: RequestBuilder) -> HttpResult<S, E> {
-    let response = c.send().await?;
+pub(crate) async fn make_request<S: DeserializeOwned, E: DeserializeOwned>(c: RequestBuilder, cache: Option<&ResponseCache>) -> HttpResult<S, E> {
+    let response = send_request(c, cache).await?;
 
-    return match response.status().as_u16() {
-        c if c == 429u16 => Err(HttpError::RateLimited),
+    match response.status().as_u16() {
+        429u16 => Err(HttpError::RateLimited),
         c if c >= 500u16 => Err(HttpError::InternalServerError(response.text().await?)),
         200u16 => {
             let data = response.json::<S>().await?;
@@ -122,12 +198,54 @@ pub(crate) async fn make_request<S: DeserializeOwned, E: DeserializeOwned>(c: Re
     }
 }
 
+/// Runs up to `concurrency` requests at the same time keeping their order, the first error is returned
+/// as soon as it arrives and the remaining requests are dropped without being sent
+///
+/// Requests are collected before any of them runs and polled without stream combinators,
+/// closures held across awaits would make the returned future not `Send`
+#[cfg(feature = "default")]
+pub(crate) async fn collect_all<T, E, F>(requests: impl IntoIterator<Item = F>, concurrency: usize) -> HttpResult<Vec<T>, E>
+where
+    F: Future<Output = HttpResult<T, E>>
+{
+    let mut pending = requests.into_iter().collect::<Vec<F>>().into_iter();
+    let mut running = FuturesOrdered::new();
+    let mut items = Vec::with_capacity(pending.len());
+
+    loop {
+        while running.len() < concurrency.max(1) {
+            match pending.next() {
+                Some(request) => running.push_back(request),
+                None => break
+            }
+        }
+
+        match running.next().await {
+            Some(Ok(Ok(item))) => items.push(item),
+            Some(Ok(Err(why))) => return Ok(Err(why)),
+            Some(Err(why)) => return Err(why),
+            None => return Ok(Ok(items))
+        }
+    }
+}
+
 const BASE_ENDPOINT: &str = "https://api.ksoft.si";
 
 pub(crate) fn endpoint(to: impl AsRef<str>) -> String {
     format!("{}{}", BASE_ENDPOINT, to.as_ref())
 }
 
+/// Builds an endpoint appending path segments escaped, so they cannot alter the path
+pub(crate) fn endpoint_segments(to: impl AsRef<str>, segments: &[&str]) -> String {
+    let mut url = reqwest::Url::parse(&endpoint(to)).expect("Invalid endpoint");
+    url.path_segments_mut()
+        .expect("Endpoint cannot be a base")
+        .pop_if_empty()
+        .extend(segments);
+
+    url.to_string()
+}
+
 /// KSoft.rs base http response, not all methods return this
 pub type HttpResult<S, E> = Result<ApiResponse<S, E>, HttpError>;
 
@@ -152,13 +270,41 @@ impl Error for HttpError {}
 impl Display for HttpError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            Self::RequestFailed(why) => write!(f, "Request failed: {}", why.to_string()),
+            Self::RequestFailed(why) => write!(f, "Request failed: {}", why),
             Self::InternalServerError(why) => write!(f, "Internal server error: {}", why),
             Self::RateLimited => write!(f, "KSoft server responded with code 429 (Ratelimited)")
         }
     }
 }
 
+#[derive(Debug)]
+pub enum DownloadError {
+    RequestFailed(reqwest::Error),
+    ReadFailed(std::io::Error),
+    UnsuccessfulResponse(u16),
+    /// The file exceeds the size limit, in bytes
+    TooLarge(usize)
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(e: reqwest::Error) -> Self {
+        DownloadError::RequestFailed(e)
+    }
+}
+
+impl Error for DownloadError {}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::RequestFailed(why) => write!(f, "Request failed: {}", why),
+            Self::ReadFailed(why) => write!(f, "Failed to read response body: {}", why),
+            Self::UnsuccessfulResponse(code) => write!(f, "Server responded with code {}", code),
+            Self::TooLarge(limit) => write!(f, "File exceeds the size limit of {} bytes", limit)
+        }
+    }
+}
+
 #[cfg(feature = "default")]
 #[async_trait]
 pub trait EventHandler: Send + Sync + 'static {
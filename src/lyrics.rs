@@ -0,0 +1,614 @@
+//! Lyrics utilities, synced lyrics timelines, LRC files and pagination
+use std::{
+    fmt::Write,
+    str::FromStr,
+    time::Duration
+};
+use crate::{
+    model::music::{LyricsData, LyricsSingalong},
+    utils::{normalize, similarity}
+};
+
+/// Max length of a Discord message
+pub const MESSAGE_LIMIT: usize = 2000;
+/// Max length of a Discord embed description
+pub const EMBED_LIMIT: usize = crate::embed::DESCRIPTION_LIMIT;
+
+/// Minimum similarity between a lyrics line and an LRC line for them to be aligned
+const ALIGN_THRESHOLD: f64 = 0.6;
+/// LRC lines looked ahead of the last aligned one when aligning a lyrics line
+const ALIGN_LOOKAHEAD: usize = 8;
+
+/// Line of synced lyrics
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncedLine {
+    pub start: Duration,
+    pub length: Duration,
+    pub line: String
+}
+
+impl SyncedLine {
+    pub fn end(&self) -> Duration {
+        self.start.saturating_add(self.length)
+    }
+}
+
+/// Timeline of synced lyrics, ordered by start
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let synced = lyrics.synced();
+/// if let Some(line) = synced.line_at(player_position) {
+///     //show line.line as the current karaoke line
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncedLyrics {
+    lines: Vec<SyncedLine>
+}
+
+impl SyncedLyrics {
+    pub fn new(mut lines: Vec<SyncedLine>) -> Self {
+        lines.sort_by_key(|l| l.start);
+
+        Self {
+            lines
+        }
+    }
+
+    /// Builds the timeline from KSoft singalong data, entries without a valid start are skipped.
+    /// If an entry has no duration, it lasts until the next one starts
+    pub fn from_singalong(singalong: &[LyricsSingalong]) -> Self {
+        Self::from_starts(singalong.iter()
+            .filter_map(|s| {
+                let start = s.milliseconds.as_deref()
+                    .and_then(parse_millis)
+                    .or_else(|| s.lrc_timestamp.as_deref().and_then(parse_timestamp))?;
+                let length = s.duration.as_deref().and_then(parse_millis);
+
+                Some((start, length, s.line.clone().unwrap_or_default()))
+            })
+            .collect())
+    }
+
+    /// Parses an `.lrc` file, lines without timestamps and metadata tags are skipped,
+    /// except for `[offset:]`, which is applied to every line
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let synced = SyncedLyrics::parse_lrc(&std::fs::read_to_string("song.lrc")?);
+    /// ```
+    pub fn parse_lrc(lrc: &str) -> Self {
+        let mut offset = 0i64;
+        let mut lines = Vec::new();
+
+        for raw in lrc.lines() {
+            let mut rest = raw.trim();
+            let mut starts = Vec::new();
+
+            while let Some(tag) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                let (tag, after) = tag;
+                match parse_timestamp(tag) {
+                    Some(start) => starts.push(start),
+                    None => if let Some(value) = tag.strip_prefix("offset:") {
+                        offset = value.trim().parse().unwrap_or(offset);
+                    }
+                }
+                rest = after;
+            }
+
+            let text = strip_word_timestamps(rest);
+            lines.extend(starts.into_iter().map(|start| (start, None, text.clone())));
+        }
+
+        // A positive offset makes lyrics appear sooner
+        let shift = |start: Duration| match offset {
+            o if o >= 0 => start.saturating_sub(Duration::from_millis(o as u64)),
+            o => start.checked_add(Duration::from_millis(o.unsigned_abs())).unwrap_or(Duration::MAX)
+        };
+
+        Self::from_starts(lines.into_iter()
+            .map(|(start, length, line)| (shift(start), length, line))
+            .collect())
+    }
+
+    /// Gives timing to plain lyrics using the lines of another timeline, a local LRC file for example
+    ///
+    /// Lyrics lines are matched in order to the most similar timed line, lines that
+    /// could not be matched are placed evenly between their matched neighbours, or start
+    /// with the first or last matched line if they come before or after every match
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let lrc = SyncedLyrics::parse_lrc(&uploaded_file);
+    /// let synced = SyncedLyrics::align(&lyrics.lyrics, &lrc);
+    /// ```
+    pub fn align(lyrics: &str, timed: &SyncedLyrics) -> Self {
+        let texts = lyrics.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>();
+        let timed_lines = timed.lines.iter()
+            .map(|l| normalize(&l.line))
+            .collect::<Vec<_>>();
+
+        let mut next = 0;
+        let starts = texts.iter()
+            .map(|text| {
+                let text = normalize(text);
+                let (index, score) = timed_lines.iter()
+                    .enumerate()
+                    .skip(next)
+                    .take(ALIGN_LOOKAHEAD)
+                    .map(|(i, line)| (i, similarity(&text, line)))
+                    .fold(None, |best: Option<(usize, f64)>, (i, score)| match best {
+                        Some((_, best_score)) if best_score >= score => best,
+                        _ => Some((i, score))
+                    })?;
+
+                if score < ALIGN_THRESHOLD { return None }
+                next = index + 1;
+                Some(timed.lines[index].start)
+            })
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+        for (i, text) in texts.iter().enumerate() {
+            let start = match starts[i] {
+                Some(start) => start,
+                None => {
+                    let previous = starts[..i].iter().enumerate().rev().find_map(|(j, s)| s.map(|s| (j, s)));
+                    let following = starts[i + 1..].iter().enumerate().find_map(|(j, s)| s.map(|s| (i + 1 + j, s)));
+                    match (previous, following) {
+                        (Some((p, ps)), Some((f, fs))) => ps + fs.saturating_sub(ps) / (f - p) as u32 * (i - p) as u32,
+                        // Lines before the first match or after the last one keep the closest timing
+                        (Some((_, ps)), None) => ps,
+                        (None, Some((_, fs))) => fs,
+                        (None, None) => Duration::ZERO
+                    }
+                }
+            };
+            lines.push((start, None, text.to_string()));
+        }
+
+        Self::from_starts(lines)
+    }
+
+    /// Builds the timeline, lines without length last until the next one starts
+    fn from_starts(mut lines: Vec<(Duration, Option<Duration>, String)>) -> Self {
+        lines.sort_by_key(|(start, _, _)| *start);
+        let nexts = lines.iter()
+            .skip(1)
+            .map(|(start, _, _)| Some(*start))
+            .chain(Some(None))
+            .collect::<Vec<_>>();
+
+        Self {
+            lines: lines.into_iter()
+                .zip(nexts)
+                .map(|((start, length, line), next)| SyncedLine {
+                    length: length
+                        .or_else(|| next.map(|next| next.saturating_sub(start)))
+                        .unwrap_or_default(),
+                    start,
+                    line
+                })
+                .collect()
+        }
+    }
+
+    pub fn lines(&self) -> &[SyncedLine] {
+        &self.lines
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SyncedLine> {
+        self.lines.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Gets the line being sung at a given playback position
+    ///
+    /// The last line keeps being returned after it ends if its length is unknown
+    pub fn line_at(&self, position: Duration) -> Option<&SyncedLine> {
+        let index = self.lines.partition_point(|l| l.start <= position).checked_sub(1)?;
+        let line = &self.lines[index];
+
+        if (line.length.is_zero() && index + 1 == self.lines.len()) || position < line.end() {
+            Some(line)
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the lines that are sung at any moment between `from` and `to`
+    pub fn window(&self, from: Duration, to: Duration) -> impl Iterator<Item = &SyncedLine> {
+        self.lines.iter()
+            .filter(move |l| l.start < to && (l.end() > from || l.start >= from))
+    }
+
+    /// Exports the timeline as a standard `.lrc` file
+    pub fn to_lrc(&self) -> String {
+        let mut lrc = String::new();
+        for line in &self.lines {
+            let _ = writeln!(lrc, "[{}]{}", format_timestamp(line.start), line.line);
+        }
+
+        lrc
+    }
+}
+
+/// Page of lyrics, `index` starts at 0
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LyricsPage {
+    pub index: usize,
+    pub total: usize,
+    pub content: String
+}
+
+impl LyricsPage {
+    /// Page number starting at 1, to be shown to users
+    pub fn number(&self) -> usize {
+        self.index + 1
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.index == 0
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.index + 1 >= self.total
+    }
+}
+
+/// Splits lyrics into pages of at most `max_len` characters
+///
+/// Pages are split between stanzas when possible, then between lines. A line is only
+/// split if it does not fit in a page by itself
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let pages = paginate(&lyrics.lyrics, EMBED_LIMIT);
+/// let page = &pages[current];
+/// embed.description(&page.content).footer(format!("Page {}/{}", page.number(), page.total));
+/// ```
+pub fn paginate(lyrics: &str, max_len: usize) -> Vec<LyricsPage> {
+    let max_len = max_len.max(1);
+    let mut pages = Vec::new();
+    let mut current = String::new();
+
+    let normalized = lyrics.replace("\r\n", "\n");
+    let stanzas = normalized.split("\n\n")
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+
+    for stanza in stanzas {
+        if fits(&current, stanza, "\n\n", max_len) {
+            push(&mut current, stanza, "\n\n");
+            continue;
+        }
+        if !current.is_empty() {
+            pages.push(std::mem::take(&mut current));
+        }
+        if len(stanza) <= max_len {
+            current.push_str(stanza);
+            continue;
+        }
+
+        for line in stanza.lines() {
+            if fits(&current, line, "\n", max_len) {
+                push(&mut current, line, "\n");
+                continue;
+            }
+            if !current.is_empty() {
+                pages.push(std::mem::take(&mut current));
+            }
+            if len(line) <= max_len {
+                current.push_str(line);
+                continue;
+            }
+
+            let mut chunks = split_line(line, max_len);
+            current = chunks.pop().unwrap_or_default();
+            pages.extend(chunks);
+        }
+    }
+
+    if !current.is_empty() {
+        pages.push(current);
+    }
+
+    let total = pages.len();
+    pages.into_iter()
+        .enumerate()
+        .map(|(index, content)| LyricsPage {
+            index,
+            total,
+            content
+        })
+        .collect()
+}
+
+fn len(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn fits(current: &str, text: &str, separator: &str, max_len: usize) -> bool {
+    current.is_empty() && len(text) <= max_len
+        || !current.is_empty() && len(current) + len(separator) + len(text) <= max_len
+}
+
+fn push(current: &mut String, text: &str, separator: &str) {
+    if !current.is_empty() {
+        current.push_str(separator);
+    }
+    current.push_str(text);
+}
+
+/// Splits a line too long for a page, between words when possible
+fn split_line(line: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = line.trim();
+
+    while len(rest) > max_len {
+        let limit = rest.char_indices().nth(max_len).map_or(rest.len(), |(i, _)| i);
+        // A chunk ending right before a space is already split between words
+        let cut = if rest[limit..].starts_with(char::is_whitespace) {
+            limit
+        } else {
+            rest[..limit].rfind(char::is_whitespace).filter(|i| *i > 0).unwrap_or(limit)
+        };
+        chunks.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+impl FromStr for SyncedLyrics {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse_lrc(s))
+    }
+}
+
+impl LyricsData {
+    /// Parses the singalong data into a synced lyrics timeline
+    pub fn synced(&self) -> SyncedLyrics {
+        SyncedLyrics::from_singalong(&self.singalong)
+    }
+
+    /// Splits the lyrics into pages of at most `max_len` characters, see [`paginate`](paginate)
+    pub fn pages(&self, max_len: usize) -> Vec<LyricsPage> {
+        paginate(&self.lyrics, max_len)
+    }
+
+    /// Gets the synced lyrics, aligning the plain lyrics to a local timeline if there is no singalong data
+    pub fn synced_or_aligned(&self, lrc: &SyncedLyrics) -> SyncedLyrics {
+        match self.synced() {
+            synced if synced.is_empty() => SyncedLyrics::align(&self.lyrics, lrc),
+            synced => synced
+        }
+    }
+}
+
+/// Removes enhanced LRC word timestamps, as `<mm:ss.xx>`
+fn strip_word_timestamps(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(open) = rest.find('<') {
+        match rest[open..].find('>') {
+            Some(close) if parse_timestamp(&rest[open + 1..open + close]).is_some() => {
+                text.push_str(&rest[..open]);
+                rest = &rest[open + close + 1..];
+            },
+            _ => {
+                text.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+
+    text.push_str(rest);
+    text.trim().to_string()
+}
+
+fn parse_millis(millis: &str) -> Option<Duration> {
+    let millis = millis.trim().parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(millis / 1000.0).ok()
+}
+
+/// Parses an LRC timestamp, as `[mm:ss.xx]`, `mm:ss.xxx` or `mm:ss`
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let timestamp = timestamp.trim().trim_start_matches('[').trim_end_matches(']');
+    let (minutes, seconds) = timestamp.split_once(':')?;
+
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let seconds = seconds.trim().replace(':', ".").parse::<f64>().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+
+    Duration::from_secs(minutes.checked_mul(60)?).checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
+
+/// Formats a position as an LRC timestamp, `mm:ss.xx`
+pub(crate) fn format_timestamp(position: Duration) -> String {
+    let centis = position.as_millis() / 10;
+    format!("{:02}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(position: Duration) -> u64 {
+        (position.as_secs_f64() * 1000.0).round() as u64
+    }
+
+    fn timeline(synced: &SyncedLyrics) -> Vec<(u64, u64, &str)> {
+        synced.iter()
+            .map(|l| (millis(l.start), millis(l.length), l.line.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn singalong_payload() {
+        let singalong: Vec<LyricsSingalong> = serde_json::from_str(r#"[
+            {"lrc_timestamp": "[00:10.48]", "milliseconds": "10480", "duration": "", "line": "Fonsi, DY"},
+            {"lrc_timestamp": "[00:07.29]", "milliseconds": "7290", "duration": "3190", "line": "Ay"},
+            {"lrc_timestamp": "[00:13.12]", "milliseconds": null, "duration": null, "line": "Oh, oh no, oh no"},
+            {"lrc_timestamp": null, "milliseconds": null, "duration": "1000", "line": "No start"},
+            {"lrc_timestamp": "[00:16.00]", "milliseconds": "1e30", "duration": "-5", "line": null}
+        ]"#).unwrap();
+
+        let synced = SyncedLyrics::from_singalong(&singalong);
+        assert_eq!(timeline(&synced), vec![
+            (7290, 3190, "Ay"),
+            (10480, 2640, "Fonsi, DY"),
+            (13120, 2880, "Oh, oh no, oh no"),
+            (16000, 0, "")
+        ]);
+
+        assert_eq!(synced.line_at(Duration::from_millis(8000)).map(|l| l.line.as_str()), Some("Ay"));
+        assert_eq!(synced.line_at(Duration::from_millis(5000)), None);
+        assert_eq!(synced.line_at(Duration::from_secs(60)).map(|l| l.line.as_str()), Some(""));
+    }
+
+    #[test]
+    fn singalong_out_of_range_duration() {
+        let singalong: Vec<LyricsSingalong> = serde_json::from_str(r#"[
+            {"lrc_timestamp": "[00:01.00]", "milliseconds": "1000", "duration": "1e30", "line": "Too long"}
+        ]"#).unwrap();
+
+        let synced = SyncedLyrics::from_singalong(&singalong);
+        assert_eq!(timeline(&synced), vec![(1000, 0, "Too long")]);
+    }
+
+    #[test]
+    fn lrc_file() {
+        let lrc = "[ti:Despacito]\n\
+            [ar:Luis Fonsi]\n\
+            [offset:+500]\n\
+            [00:07.29]Ay\n\
+            [00:10.48][01:10.48]Fonsi, DY\n\
+            [00:13.12]<00:13.12>Oh, <00:14.00>oh no\n\
+            not a timed line\n\
+            [00:20.00]\n";
+
+        let synced = SyncedLyrics::parse_lrc(lrc);
+        assert_eq!(timeline(&synced), vec![
+            (6790, 3190, "Ay"),
+            (9980, 2640, "Fonsi, DY"),
+            (12620, 6880, "Oh, oh no"),
+            (19500, 50480, ""),
+            (69980, 0, "Fonsi, DY")
+        ]);
+        assert_eq!(SyncedLyrics::parse_lrc(&synced.to_lrc()), synced);
+    }
+
+    #[test]
+    fn lrc_negative_offset() {
+        let synced = SyncedLyrics::parse_lrc("[offset:-1500]\n[00:01.00]Late");
+        assert_eq!(timeline(&synced), vec![(2500, 0, "Late")]);
+
+        let synced = SyncedLyrics::parse_lrc("[offset:-9223372036854775808]\n[00:01.00]Very late");
+        assert_eq!(synced.len(), 1);
+    }
+
+    #[test]
+    fn lrc_invalid_timestamps() {
+        let synced = SyncedLyrics::parse_lrc("[99999999999999999999:00.00]Too many minutes\n\
+            [18446744073709551615:00.00]Overflowing minutes\n\
+            [00:75.00]Too many seconds\n\
+            [00:1e30]Huge seconds\n\
+            [ab:cd]Not a number");
+
+        assert!(synced.is_empty());
+        assert_eq!(parse_timestamp("[03:25.50]"), Some(Duration::from_millis(205_500)));
+        assert_eq!(parse_timestamp("03:25"), Some(Duration::from_secs(205)));
+    }
+
+    #[test]
+    fn align_keeps_unmatched_lines() {
+        let timed = SyncedLyrics::parse_lrc("[00:10.00]Hello darkness, my old friend\n\
+            [00:15.00]I've come to talk with you again\n\
+            [00:20.00]Because a vision softly creeping");
+        let lyrics = "Intro\n\
+            Hello darkness, my old friend\n\
+            Mmm\n\
+            I've come to talk with you again\n\
+            \n\
+            Because a vision softly creeping\n\
+            Outro";
+
+        let synced = SyncedLyrics::align(lyrics, &timed);
+        let starts = synced.iter()
+            .map(|l| (millis(l.start), l.line.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![
+            (10000, "Intro"),
+            (10000, "Hello darkness, my old friend"),
+            (12500, "Mmm"),
+            (15000, "I've come to talk with you again"),
+            (20000, "Because a vision softly creeping"),
+            (20000, "Outro")
+        ]);
+    }
+
+    const LYRICS: &str = "Hello darkness, my old friend\r\n\
+        I've come to talk with you again\r\n\
+        \r\n\
+        Because a vision softly creeping\r\n\
+        Left its seeds while I was sleeping\r\n\
+        \r\n\
+        \r\n\
+        And the vision that was planted in my brain\r\n\
+        Still remains";
+
+    #[test]
+    fn paginate_by_stanza() {
+        let pages = paginate(LYRICS, 70);
+        let contents = pages.iter().map(|p| p.content.as_str()).collect::<Vec<_>>();
+        assert_eq!(contents, vec![
+            "Hello darkness, my old friend\nI've come to talk with you again",
+            "Because a vision softly creeping\nLeft its seeds while I was sleeping",
+            "And the vision that was planted in my brain\nStill remains"
+        ]);
+
+        assert!(pages[0].is_first() && !pages[0].is_last());
+        assert_eq!((pages[2].number(), pages[2].total), (3, 3));
+        assert!(pages[2].is_last());
+
+        assert_eq!(paginate(LYRICS, MESSAGE_LIMIT).len(), 1);
+        assert!(paginate("  \n\n ", MESSAGE_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn paginate_splits_long_stanzas_and_lines() {
+        let pages = paginate(LYRICS, 40);
+        assert!(pages.iter().all(|p| p.content.chars().count() <= 40));
+        assert_eq!(pages[0].content, "Hello darkness, my old friend");
+        assert_eq!(pages[4].content, "And the vision that was planted in my");
+        assert_eq!(pages[5].content, "brain\nStill remains");
+
+        let pages = paginate("ñññññ ñññññ ñññññ", 11);
+        let contents = pages.iter().map(|p| p.content.as_str()).collect::<Vec<_>>();
+        assert_eq!(contents, vec!["ñññññ ñññññ", "ñññññ"]);
+    }
+}
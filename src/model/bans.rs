@@ -1,5 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+/// Types that can be used as a Discord user id on ban methods
+///
+/// Implemented for `u64` and, with the `twilight` feature, for twilight's `Id<UserMarker>`
+pub trait UserId {
+    fn user_id(self) -> u64;
+}
+
+impl UserId for u64 {
+    fn user_id(self) -> u64 {
+        self
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct BanList {
     pub ban_count: u64,
@@ -92,3 +105,17 @@ pub struct BanUpdate {
     pub moderator: u64,
     pub active: bool
 }
+
+#[cfg(test)]
+mod tests {
+    use super::UserId;
+
+    fn user_id(user: impl UserId) -> u64 {
+        user.user_id()
+    }
+
+    #[test]
+    fn integer_literals_are_user_ids() {
+        assert_eq!(user_id(123456789012345678), 123456789012345678);
+    }
+}
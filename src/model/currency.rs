@@ -0,0 +1,422 @@
+//! ISO 4217 currencies and exact decimal amounts for currency conversions
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr
+};
+
+/// Active ISO 4217 currencies: code, name and digits after the decimal separator
+const CURRENCIES: &[(&str, &str, u32)] = &[
+    ("AED", "UAE Dirham", 2),
+    ("AFN", "Afghani", 2),
+    ("ALL", "Lek", 2),
+    ("AMD", "Armenian Dram", 2),
+    ("ANG", "Netherlands Antillean Guilder", 2),
+    ("AOA", "Kwanza", 2),
+    ("ARS", "Argentine Peso", 2),
+    ("AUD", "Australian Dollar", 2),
+    ("AWG", "Aruban Florin", 2),
+    ("AZN", "Azerbaijan Manat", 2),
+    ("BAM", "Convertible Mark", 2),
+    ("BBD", "Barbados Dollar", 2),
+    ("BDT", "Taka", 2),
+    ("BGN", "Bulgarian Lev", 2),
+    ("BHD", "Bahraini Dinar", 3),
+    ("BIF", "Burundi Franc", 0),
+    ("BMD", "Bermudian Dollar", 2),
+    ("BND", "Brunei Dollar", 2),
+    ("BOB", "Boliviano", 2),
+    ("BRL", "Brazilian Real", 2),
+    ("BSD", "Bahamian Dollar", 2),
+    ("BTN", "Ngultrum", 2),
+    ("BWP", "Pula", 2),
+    ("BYN", "Belarusian Ruble", 2),
+    ("BZD", "Belize Dollar", 2),
+    ("CAD", "Canadian Dollar", 2),
+    ("CDF", "Congolese Franc", 2),
+    ("CHF", "Swiss Franc", 2),
+    ("CLP", "Chilean Peso", 0),
+    ("CNY", "Yuan Renminbi", 2),
+    ("COP", "Colombian Peso", 2),
+    ("CRC", "Costa Rican Colon", 2),
+    ("CUP", "Cuban Peso", 2),
+    ("CVE", "Cabo Verde Escudo", 2),
+    ("CZK", "Czech Koruna", 2),
+    ("DJF", "Djibouti Franc", 0),
+    ("DKK", "Danish Krone", 2),
+    ("DOP", "Dominican Peso", 2),
+    ("DZD", "Algerian Dinar", 2),
+    ("EGP", "Egyptian Pound", 2),
+    ("ERN", "Nakfa", 2),
+    ("ETB", "Ethiopian Birr", 2),
+    ("EUR", "Euro", 2),
+    ("FJD", "Fiji Dollar", 2),
+    ("FKP", "Falkland Islands Pound", 2),
+    ("GBP", "Pound Sterling", 2),
+    ("GEL", "Lari", 2),
+    ("GHS", "Ghana Cedi", 2),
+    ("GIP", "Gibraltar Pound", 2),
+    ("GMD", "Dalasi", 2),
+    ("GNF", "Guinean Franc", 0),
+    ("GTQ", "Quetzal", 2),
+    ("GYD", "Guyana Dollar", 2),
+    ("HKD", "Hong Kong Dollar", 2),
+    ("HNL", "Lempira", 2),
+    ("HTG", "Gourde", 2),
+    ("HUF", "Forint", 2),
+    ("IDR", "Rupiah", 2),
+    ("ILS", "New Israeli Sheqel", 2),
+    ("INR", "Indian Rupee", 2),
+    ("IQD", "Iraqi Dinar", 3),
+    ("IRR", "Iranian Rial", 2),
+    ("ISK", "Iceland Krona", 0),
+    ("JMD", "Jamaican Dollar", 2),
+    ("JOD", "Jordanian Dinar", 3),
+    ("JPY", "Yen", 0),
+    ("KES", "Kenyan Shilling", 2),
+    ("KGS", "Som", 2),
+    ("KHR", "Riel", 2),
+    ("KMF", "Comorian Franc", 0),
+    ("KPW", "North Korean Won", 2),
+    ("KRW", "Won", 0),
+    ("KWD", "Kuwaiti Dinar", 3),
+    ("KYD", "Cayman Islands Dollar", 2),
+    ("KZT", "Tenge", 2),
+    ("LAK", "Lao Kip", 2),
+    ("LBP", "Lebanese Pound", 2),
+    ("LKR", "Sri Lanka Rupee", 2),
+    ("LRD", "Liberian Dollar", 2),
+    ("LSL", "Loti", 2),
+    ("LYD", "Libyan Dinar", 3),
+    ("MAD", "Moroccan Dirham", 2),
+    ("MDL", "Moldovan Leu", 2),
+    ("MGA", "Malagasy Ariary", 2),
+    ("MKD", "Denar", 2),
+    ("MMK", "Kyat", 2),
+    ("MNT", "Tugrik", 2),
+    ("MOP", "Pataca", 2),
+    ("MRU", "Ouguiya", 2),
+    ("MUR", "Mauritius Rupee", 2),
+    ("MVR", "Rufiyaa", 2),
+    ("MWK", "Malawi Kwacha", 2),
+    ("MXN", "Mexican Peso", 2),
+    ("MYR", "Malaysian Ringgit", 2),
+    ("MZN", "Mozambique Metical", 2),
+    ("NAD", "Namibia Dollar", 2),
+    ("NGN", "Naira", 2),
+    ("NIO", "Cordoba Oro", 2),
+    ("NOK", "Norwegian Krone", 2),
+    ("NPR", "Nepalese Rupee", 2),
+    ("NZD", "New Zealand Dollar", 2),
+    ("OMR", "Rial Omani", 3),
+    ("PAB", "Balboa", 2),
+    ("PEN", "Sol", 2),
+    ("PGK", "Kina", 2),
+    ("PHP", "Philippine Peso", 2),
+    ("PKR", "Pakistan Rupee", 2),
+    ("PLN", "Zloty", 2),
+    ("PYG", "Guarani", 0),
+    ("QAR", "Qatari Rial", 2),
+    ("RON", "Romanian Leu", 2),
+    ("RSD", "Serbian Dinar", 2),
+    ("RUB", "Russian Ruble", 2),
+    ("RWF", "Rwanda Franc", 0),
+    ("SAR", "Saudi Riyal", 2),
+    ("SBD", "Solomon Islands Dollar", 2),
+    ("SCR", "Seychelles Rupee", 2),
+    ("SDG", "Sudanese Pound", 2),
+    ("SEK", "Swedish Krona", 2),
+    ("SGD", "Singapore Dollar", 2),
+    ("SHP", "Saint Helena Pound", 2),
+    ("SLE", "Leone", 2),
+    ("SOS", "Somali Shilling", 2),
+    ("SRD", "Surinam Dollar", 2),
+    ("SSP", "South Sudanese Pound", 2),
+    ("STN", "Dobra", 2),
+    ("SVC", "El Salvador Colon", 2),
+    ("SYP", "Syrian Pound", 2),
+    ("SZL", "Lilangeni", 2),
+    ("THB", "Baht", 2),
+    ("TJS", "Somoni", 2),
+    ("TMT", "Turkmenistan New Manat", 2),
+    ("TND", "Tunisian Dinar", 3),
+    ("TOP", "Pa'anga", 2),
+    ("TRY", "Turkish Lira", 2),
+    ("TTD", "Trinidad and Tobago Dollar", 2),
+    ("TWD", "New Taiwan Dollar", 2),
+    ("TZS", "Tanzanian Shilling", 2),
+    ("UAH", "Hryvnia", 2),
+    ("UGX", "Uganda Shilling", 0),
+    ("USD", "US Dollar", 2),
+    ("UYU", "Peso Uruguayo", 2),
+    ("UZS", "Uzbekistan Sum", 2),
+    ("VES", "Bolivar Soberano", 2),
+    ("VND", "Dong", 0),
+    ("VUV", "Vatu", 0),
+    ("WST", "Tala", 2),
+    ("XAF", "CFA Franc BEAC", 0),
+    ("XCD", "East Caribbean Dollar", 2),
+    ("XOF", "CFA Franc BCEAO", 0),
+    ("XPF", "CFP Franc", 0),
+    ("YER", "Yemeni Rial", 2),
+    ("ZAR", "Rand", 2),
+    ("ZMW", "Zambian Kwacha", 2),
+    ("ZWL", "Zimbabwe Dollar", 2)
+];
+
+/// ISO 4217 currency, only currencies in the table of known codes can be created
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let currency: Currency = "usd".parse()?;
+/// assert_eq!(currency, Currency::USD);
+/// assert_eq!(currency.name(), "US Dollar");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Currency(&'static str);
+
+macro_rules! currency_constants {
+    ($($code: ident),*) => {
+        impl Currency {
+            $(pub const $code: Currency = Currency(stringify!($code));)*
+        }
+    }
+}
+
+currency_constants!(AUD, BRL, CAD, CHF, CNY, EUR, GBP, INR, JPY, MXN, RUB, USD);
+
+impl Currency {
+    pub fn new(code: impl AsRef<str>) -> Result<Self, CurrencyError> {
+        let code = code.as_ref().trim();
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(CurrencyError::InvalidCode(code.to_string()));
+        }
+
+        let code = code.to_ascii_uppercase();
+        Self::find(&code)
+            .map(|(code, _, _)| Currency(code))
+            .ok_or(CurrencyError::Unknown(code))
+    }
+
+    fn find(code: &str) -> Option<&'static (&'static str, &'static str, u32)> {
+        CURRENCIES.binary_search_by(|(c, _, _)| (*c).cmp(code))
+            .ok()
+            .map(|i| &CURRENCIES[i])
+    }
+
+    /// Three letter code, like `USD`
+    pub fn code(&self) -> &'static str {
+        self.0
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::find(self.0).map_or("", |(_, name, _)| name)
+    }
+
+    /// Digits after the decimal separator, 2 for cents
+    pub fn minor_units(&self) -> u32 {
+        Self::find(self.0).map_or(2, |(_, _, units)| *units)
+    }
+
+    /// Every known currency
+    pub fn all() -> impl Iterator<Item = Currency> {
+        CURRENCIES.iter().map(|(code, _, _)| Currency(code))
+    }
+}
+
+impl FromStr for Currency {
+    type Err = CurrencyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CurrencyError {
+    /// Not a three letter code
+    InvalidCode(String),
+    /// Not a known ISO 4217 currency
+    Unknown(String)
+}
+
+impl Error for CurrencyError {}
+
+impl Display for CurrencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidCode(code) => write!(f, "{:?} is not a three letter currency code", code),
+            Self::Unknown(code) => write!(f, "{} is not a known ISO 4217 currency", code)
+        }
+    }
+}
+
+/// Exact decimal amount, `mantissa * 10^-scale`
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let amount: Amount = "19.99".parse()?;
+/// assert_eq!(amount, Amount::new(1999, 2));
+/// assert_eq!(Amount::from_minor_units(1999, Currency::USD).to_string(), "19.99");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Amount {
+    mantissa: i128,
+    scale: u32
+}
+
+impl Amount {
+    /// Max digits after the decimal separator
+    pub const MAX_SCALE: u32 = 18;
+
+    /// Creates an amount, panics if `scale` is over [`MAX_SCALE`](Amount::MAX_SCALE)
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        if scale > Self::MAX_SCALE { panic!("Amount scale cannot be over {}", Self::MAX_SCALE) }
+
+        Self {
+            mantissa,
+            scale
+        }
+    }
+
+    /// Creates an amount from the smallest unit of a currency, like cents
+    pub fn from_minor_units(units: i64, currency: Currency) -> Self {
+        Self::new(units as i128, currency.minor_units())
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+}
+
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AmountError(s.to_string());
+        let text = s.trim();
+
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text))
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (digits, "")
+        };
+
+        if integer.is_empty() && fraction.is_empty()
+            || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+            || fraction.len() > Self::MAX_SCALE as usize {
+            return Err(invalid());
+        }
+
+        let mantissa = integer.chars()
+            .chain(fraction.chars())
+            .try_fold(0i128, |n, c| n.checked_mul(10)?.checked_add(c.to_digit(10)? as i128))
+            .ok_or_else(invalid)?;
+
+        Ok(Self::new(if negative { -mantissa } else { mantissa }, fraction.len() as u32))
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+macro_rules! amount_from_integer {
+    ($($t: ty),*) => {
+        $(
+            impl From<$t> for Amount {
+                fn from(n: $t) -> Self {
+                    Self::new(n as i128, 0)
+                }
+            }
+        )*
+    }
+}
+
+amount_from_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// Error parsing an [`Amount`](Amount), with the text that could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AmountError(pub String);
+
+impl Error for AmountError {}
+
+impl Display for AmountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?} is not a valid decimal amount", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_amounts() {
+        assert_eq!("19.99".parse(), Ok(Amount::new(1999, 2)));
+        assert_eq!(" -0.5 ".parse(), Ok(Amount::new(-5, 1)));
+        assert_eq!("+100".parse(), Ok(Amount::new(100, 0)));
+        assert_eq!(".25".parse(), Ok(Amount::new(25, 2)));
+        assert_eq!("7.".parse(), Ok(Amount::new(7, 0)));
+        assert_eq!("0.000000000000000001".parse(), Ok(Amount::new(1, Amount::MAX_SCALE)));
+
+        for invalid in ["", "-", ".", "1.2.3", "1,50", "1e5", "--1", "- 1", "0.0000000000000000001", "NaN"] {
+            assert_eq!(invalid.parse::<Amount>(), Err(AmountError(invalid.to_string())), "{:?}", invalid);
+        }
+
+        let overflow = "9".repeat(40);
+        assert!(overflow.parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn display_amounts() {
+        for text in ["19.99", "-0.05", "100", "0.000000000000000001"] {
+            assert_eq!(text.parse::<Amount>().unwrap().to_string(), text);
+        }
+
+        assert_eq!(Amount::from_minor_units(1999, Currency::USD).to_string(), "19.99");
+        assert_eq!(Amount::from_minor_units(1999, Currency::JPY).to_string(), "1999");
+        assert_eq!(Amount::from(-3i32).to_string(), "-3");
+    }
+
+    #[test]
+    fn currencies() {
+        assert!(CURRENCIES.windows(2).all(|w| w[0].0 < w[1].0));
+        for currency in [Currency::AUD, Currency::BRL, Currency::CAD, Currency::CHF, Currency::CNY, Currency::EUR,
+            Currency::GBP, Currency::INR, Currency::JPY, Currency::MXN, Currency::RUB, Currency::USD] {
+            assert_eq!(Currency::new(currency.code()), Ok(currency));
+        }
+
+        assert_eq!(" usd".parse(), Ok(Currency::USD));
+        assert_eq!(Currency::USD.name(), "US Dollar");
+        assert_eq!(Currency::new("US"), Err(CurrencyError::InvalidCode("US".to_string())));
+        assert_eq!(Currency::new("xyz"), Err(CurrencyError::Unknown("XYZ".to_string())));
+    }
+}
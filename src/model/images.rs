@@ -1,4 +1,16 @@
 use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+use crate::{
+    model::ImageError,
+    prelude::SpanType,
+    utils::levenshtein
+};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Image {
@@ -23,6 +35,208 @@ pub struct RedditImage {
     pub awards: u64
 }
 
+impl RedditImage {
+    /// Upvotes minus downvotes
+    pub fn score(&self) -> i64 {
+        self.upvotes as i64 - self.downvotes as i64
+    }
+
+    /// Time elapsed since the post was created
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        Duration::from_secs_f64((now - self.created_at).max(0.0))
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        let path = self.image_url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+
+        if [".gif", ".gifv", ".mp4", ".webm"].iter().any(|ext| path.ends_with(ext)) || path.contains("v.redd.it") {
+            MediaType::Gif
+        } else {
+            MediaType::Still
+        }
+    }
+}
+
+/// Valid subreddit name, normalized without the `r/` prefix
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let subreddit = Subreddit::new("/r/memes")?;
+/// assert_eq!(subreddit.name(), "memes");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subreddit(String);
+
+impl Subreddit {
+    pub const MIN_LENGTH: usize = 2;
+    pub const MAX_LENGTH: usize = 21;
+
+    pub fn new(name: impl AsRef<str>) -> Result<Self, SubredditError> {
+        let name = name.as_ref().trim().trim_start_matches('/');
+        let name = match name.get(..2) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("r/") => &name[2..],
+            _ => name
+        };
+        let name = name.trim_end_matches('/');
+
+        if name.is_empty() { return Err(SubredditError::Empty) }
+        if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '_') {
+            return Err(SubredditError::InvalidCharacter(c));
+        }
+        if !(Self::MIN_LENGTH..=Self::MAX_LENGTH).contains(&name.len()) {
+            return Err(SubredditError::InvalidLength(name.len()));
+        }
+
+        Ok(Self(name.to_string()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Subreddit {
+    type Err = SubredditError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for Subreddit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubredditError {
+    Empty,
+    InvalidCharacter(char),
+    InvalidLength(usize)
+}
+
+impl Error for SubredditError {}
+
+impl Display for SubredditError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Empty => write!(f, "Subreddit name cannot be empty"),
+            Self::InvalidCharacter(c) => write!(f, "Subreddit name cannot contain {:?}", c),
+            Self::InvalidLength(len) => write!(f, "Subreddit name must be between {} and {} characters long, got {}",
+                                               Subreddit::MIN_LENGTH, Subreddit::MAX_LENGTH, len)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaType {
+    /// Animated images and videos
+    Gif,
+    Still
+}
+
+/// Retries made by default when looking for a reddit post matching a query
+pub const DEFAULT_REDDIT_RETRIES: usize = 5;
+
+/// Query for a random reddit post with client side filters
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let query = RedditQuery::new("memes".parse()?)
+///     .span(SpanType::Week)
+///     .min_upvotes(500)
+///     .media_type(MediaType::Still)
+///     .exclude_author("AutoModerator");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RedditQuery {
+    pub(crate) subreddit: Subreddit,
+    pub(crate) remove_nsfw: bool,
+    pub(crate) span: SpanType,
+    pub(crate) retries: usize,
+    min_upvotes: Option<u64>,
+    max_age: Option<Duration>,
+    media_type: Option<MediaType>,
+    excluded_authors: HashSet<String>,
+    min_score: Option<i64>
+}
+
+impl RedditQuery {
+    pub fn new(subreddit: Subreddit) -> Self {
+        Self {
+            subreddit,
+            remove_nsfw: true,
+            span: SpanType::Day,
+            retries: DEFAULT_REDDIT_RETRIES,
+            min_upvotes: None,
+            max_age: None,
+            media_type: None,
+            excluded_authors: HashSet::new(),
+            min_score: None
+        }
+    }
+
+    pub fn remove_nsfw(mut self, remove_nsfw: bool) -> Self {
+        self.remove_nsfw = remove_nsfw;
+        self
+    }
+
+    pub fn span(mut self, span: SpanType) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Sets how many extra posts can be requested when one does not match the filters
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn min_upvotes(mut self, upvotes: u64) -> Self {
+        self.min_upvotes = Some(upvotes);
+        self
+    }
+
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    pub fn media_type(mut self, media_type: MediaType) -> Self {
+        self.media_type = Some(media_type);
+        self
+    }
+
+    pub fn exclude_author(mut self, author: impl ToString) -> Self {
+        self.excluded_authors.insert(author.to_string().to_lowercase());
+        self
+    }
+
+    /// Sets the minimum score, upvotes minus downvotes
+    pub fn min_score(mut self, score: i64) -> Self {
+        self.min_score = Some(score);
+        self
+    }
+
+    /// Checks if a post passes all filters
+    pub fn matches(&self, image: &RedditImage) -> bool {
+        self.min_upvotes.is_none_or(|min| image.upvotes >= min)
+            && self.max_age.is_none_or(|max| image.age() <= max)
+            && self.media_type.is_none_or(|media| image.media_type() == media)
+            && !self.excluded_authors.contains(&image.author.to_lowercase())
+            && self.min_score.is_none_or(|min| image.score() >= min)
+            && !(self.remove_nsfw && image.nsfw)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct WikiHowImage {
     pub url: String,
@@ -43,3 +257,282 @@ pub struct TagList {
     pub tags: Vec<String>,
     pub nsfw_tags: Option<Vec<String>>
 }
+
+impl TagList {
+    /// Gets every tag as a model, building them from the tag lists if the server did not send them
+    pub fn into_models(self) -> Vec<TagModel> {
+        match self.models {
+            Some(models) => models,
+            None => self.tags.into_iter()
+                .map(|name| TagModel { name, nsfw: false })
+                .chain(self.nsfw_tags.unwrap_or_default().into_iter().map(|name| TagModel { name, nsfw: true }))
+                .collect()
+        }
+    }
+
+    /// Ranks the tags by how well they match a search, best match first, removing NSFW tags if `nsfw` is false
+    ///
+    /// An empty search matches every tag, sorted by name
+    pub fn ranked(self, query: impl AsRef<str>, nsfw: bool) -> Vec<TagModel> {
+        let query = query.as_ref().trim().to_lowercase();
+
+        let mut models = self.into_models().into_iter()
+            .filter(|m| nsfw || !m.nsfw)
+            .map(|m| {
+                if query.is_empty() { return ((0, 0), m) }
+
+                let name = m.name.to_lowercase();
+                let rank = if name == query {
+                    0
+                } else if name.starts_with(&query) {
+                    1
+                } else if name.contains(&query) {
+                    2
+                } else {
+                    3
+                };
+                ((rank, levenshtein(&query, &name)), m)
+            })
+            .collect::<Vec<_>>();
+
+        models.sort_by(|(a, ma), (b, mb)| a.cmp(b).then_with(|| ma.name.cmp(&mb.name)));
+        models.into_iter().map(|(_, m)| m).collect()
+    }
+}
+
+/// Image tag checked against the tags KSoft knows about, obtained from a [`TagRegistry`](TagRegistry)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tag {
+    name: String,
+    nsfw: bool
+}
+
+impl Tag {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_nsfw(&self) -> bool {
+        self.nsfw
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TagError {
+    Empty,
+    /// The tag does not exist, contains the closest existing tags
+    NotFound {
+        tag: String,
+        suggestions: Vec<String>
+    },
+    /// The tag is NSFW but NSFW tags were not allowed
+    Nsfw(String)
+}
+
+impl Error for TagError {}
+
+impl Display for TagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Empty => write!(f, "Tag cannot be empty"),
+            Self::NotFound { tag, suggestions } if suggestions.is_empty() => write!(f, "Tag {} not found", tag),
+            Self::NotFound { tag, suggestions } => write!(f, "Tag {} not found, did you mean {}?", tag, suggestions.join(", ")),
+            Self::Nsfw(tag) => write!(f, "Tag {} is NSFW", tag)
+        }
+    }
+}
+
+/// Error of a single image of a batch lookup, empty snowflakes are rejected without making a request
+#[derive(Clone, Debug)]
+pub enum ImageLookupError {
+    EmptySnowflake,
+    Api(ImageError)
+}
+
+impl From<ImageError> for ImageLookupError {
+    fn from(e: ImageError) -> Self {
+        ImageLookupError::Api(e)
+    }
+}
+
+impl Error for ImageLookupError {}
+
+impl Display for ImageLookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::EmptySnowflake => write!(f, "Snowflake id cannot be empty"),
+            Self::Api(why) => write!(f, "{}", why)
+        }
+    }
+}
+
+/// Max number of suggestions given for a tag that does not exist
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Local registry of the tags available, used to check tags without spending a request
+#[derive(Clone, Debug, Default)]
+pub struct TagRegistry {
+    tags: HashMap<String, Tag>
+}
+
+impl TagRegistry {
+    /// Checks if a tag exists, rejecting NSFW tags if `nsfw` is false
+    pub fn check(&self, tag: impl AsRef<str>, nsfw: bool) -> Result<Tag, TagError> {
+        let name = tag.as_ref().trim().to_lowercase();
+        if name.is_empty() { return Err(TagError::Empty) }
+
+        match self.tags.get(&name) {
+            Some(tag) if tag.nsfw && !nsfw => Err(TagError::Nsfw(tag.name.clone())),
+            Some(tag) => Ok(tag.clone()),
+            None => Err(TagError::NotFound {
+                suggestions: self.suggest(&name, nsfw),
+                tag: name
+            })
+        }
+    }
+
+    /// Gets the existing tags closest to the given one, closest first
+    pub fn suggest(&self, tag: impl AsRef<str>, nsfw: bool) -> Vec<String> {
+        let name = tag.as_ref().trim().to_lowercase();
+        let max_distance = (name.chars().count() / 3).max(1);
+
+        let mut candidates = self.tags.values()
+            .filter(|t| nsfw || !t.nsfw)
+            .map(|t| (levenshtein(&name, &t.name), &t.name))
+            .filter(|(distance, t)| *distance <= max_distance || t.starts_with(&name))
+            .collect::<Vec<_>>();
+
+        candidates.sort();
+        candidates.into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, t)| t.clone())
+            .collect()
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &Tag> {
+        self.tags.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+impl From<TagList> for TagRegistry {
+    fn from(list: TagList) -> Self {
+        Self {
+            tags: list.into_models().into_iter()
+                .map(|m| (m.name.to_lowercase(), Tag { name: m.name, nsfw: m.nsfw }))
+                .collect()
+        }
+    }
+}
+
+/// Max size of a downloaded image by default, matching Discord's upload limit
+pub const DEFAULT_DOWNLOAD_LIMIT: usize = 10 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct ImageDownload {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    pub extension: &'static str
+}
+
+impl ImageDownload {
+    /// Builds the download detecting its type from the content, falling back to the content-type header
+    pub(crate) fn new(bytes: Vec<u8>, content_type: Option<&str>) -> Self {
+        let mime_type = sniff_mime_type(&bytes)
+            .map(ToString::to_string)
+            .or_else(|| content_type.map(|c| c.split(';').next().unwrap_or(c).trim().to_lowercase()))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let extension = extension_for(&mime_type);
+
+        Self {
+            bytes,
+            mime_type,
+            extension
+        }
+    }
+}
+
+fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("image/avif"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("video/mp4"),
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => Some("video/webm"),
+        _ => None
+    }
+}
+
+fn extension_for(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        _ => "bin"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subreddit_names() {
+        for (input, name) in [("memes", "memes"), ("/r/memes/", "memes"), ("R/AskReddit", "AskReddit"),
+            (" r/aww ", "aww"), ("me_irl", "me_irl"), ("r/rr", "rr")] {
+            assert_eq!(Subreddit::new(input).map(|s| s.name().to_string()), Ok(name.to_string()), "{:?}", input);
+        }
+
+        assert_eq!("/r/memes".parse::<Subreddit>().unwrap().to_string(), "memes");
+    }
+
+    #[test]
+    fn invalid_subreddit_names() {
+        assert_eq!(Subreddit::new(""), Err(SubredditError::Empty));
+        assert_eq!(Subreddit::new("/r/"), Err(SubredditError::Empty));
+        assert_eq!(Subreddit::new("a"), Err(SubredditError::InvalidLength(1)));
+        assert_eq!(Subreddit::new("a".repeat(Subreddit::MAX_LENGTH + 1)), Err(SubredditError::InvalidLength(22)));
+        assert_eq!(Subreddit::new("me mes"), Err(SubredditError::InvalidCharacter(' ')));
+        assert_eq!(Subreddit::new("../admin"), Err(SubredditError::InvalidCharacter('.')));
+        assert_eq!(Subreddit::new("memes?nsfw=1"), Err(SubredditError::InvalidCharacter('?')));
+        assert_eq!(Subreddit::new("r/memes/new"), Err(SubredditError::InvalidCharacter('/')));
+        assert_eq!(Subreddit::new("ñandú"), Err(SubredditError::InvalidCharacter('ñ')));
+    }
+
+    #[test]
+    fn ranked_tags() {
+        let list = || TagList {
+            models: None,
+            tags: vec!["hotdog".to_string(), "dog".to_string(), "doge".to_string(), "cat".to_string()],
+            nsfw_tags: Some(vec!["dogging".to_string()])
+        };
+        let names = |tags: Vec<TagModel>| tags.into_iter().map(|t| t.name).collect::<Vec<_>>();
+
+        assert_eq!(names(list().ranked("Dog ", false)), vec!["dog", "doge", "hotdog", "cat"]);
+        assert_eq!(names(list().ranked("dog", true)), vec!["dog", "doge", "dogging", "hotdog", "cat"]);
+        assert_eq!(names(list().ranked("", false)), vec!["cat", "dog", "doge", "hotdog"]);
+        assert_eq!(names(list().ranked("  ", true)), vec!["cat", "dog", "doge", "dogging", "hotdog"]);
+    }
+}
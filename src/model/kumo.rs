@@ -1,4 +1,11 @@
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, de::Error as DeError};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr}
+};
+use reqwest::Url;
+use crate::model::KumoError;
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct GeoIPResponse {
@@ -23,14 +30,63 @@ pub struct GeoIPResponseData {
     pub apis: GeoIPResponseApis
 }
 
+impl GeoIPResponseData {
+    pub fn coordinates(&self) -> Coordinates {
+        Coordinates::new(self.latitude, self.longitude)
+    }
+}
+
+#[cfg(feature = "timezone")]
+impl GeoIPResponseData {
+    /// Parses the IANA time zone of the address, like `Europe/Madrid`
+    pub fn tz(&self) -> Result<chrono_tz::Tz, chrono_tz::ParseError> {
+        self.time_zone.parse()
+    }
+
+    /// Current local time at the address, None if its time zone is unknown
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(geoip)) = client.kumo.geoip(ip).await {
+    ///     if let Some(time) = geoip.data.local_time() {
+    ///         println!("It is {} there", time.format("%H:%M"));
+    ///     }
+    /// }
+    /// ```
+    pub fn local_time(&self) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+        let tz = self.tz().ok()?;
+        Some(chrono::Utc::now().with_timezone(&tz))
+    }
+}
+
+/// Links related to the location, a link that is missing or cannot be parsed is None
 #[derive(Clone, Debug, Deserialize)]
 pub struct GeoIPResponseApis {
-    pub weather: String,
-    pub gis: String,
-    #[serde(rename = "openstreetmap")]
-    pub open_street_map: String,
-    #[serde(rename = "googlemaps")]
-    pub google_maps: String
+    #[serde(default, deserialize_with = "api_url")]
+    pub weather: Option<Url>,
+    #[serde(default, deserialize_with = "api_url")]
+    pub gis: Option<Url>,
+    #[serde(rename = "openstreetmap", default, deserialize_with = "api_url")]
+    pub open_street_map: Option<Url>,
+    #[serde(rename = "googlemaps", default, deserialize_with = "api_url")]
+    pub google_maps: Option<Url>
+}
+
+/// Parses a link, links to KSoft endpoints may be a path relative to the API
+///
+/// Invalid links are None instead of an error, so one bad link does not fail the whole response
+fn api_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Url>, D::Error> {
+    let link = match Option::<String>::deserialize(deserializer)? {
+        Some(link) => link,
+        None => return Ok(None)
+    };
+
+    Ok(if link.starts_with('/') {
+        Url::parse(&crate::endpoint(&link)).ok()
+    } else {
+        Url::parse(&link).ok()
+    })
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -38,3 +94,863 @@ pub struct CurrencyConversionResponse {
     pub value: f64,
     pub pretty: String
 }
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeatherResponse<T> {
+    pub error: bool,
+    #[serde(default, alias = "status")]
+    pub code: u16,
+    pub data: T
+}
+
+/// Weather at a point in time, fields missing from a report are None
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherData {
+    /// Unix timestamp
+    pub time: u64,
+    pub summary: Option<String>,
+    pub icon: Option<String>,
+    #[serde(rename = "icon_url")]
+    pub icon_url: Option<String>,
+    pub precip_intensity: Option<f64>,
+    pub precip_probability: Option<f64>,
+    pub precip_type: Option<String>,
+    pub temperature: Option<f64>,
+    pub apparent_temperature: Option<f64>,
+    /// Only on daily reports
+    pub temperature_high: Option<f64>,
+    /// Only on daily reports
+    pub temperature_low: Option<f64>,
+    pub dew_point: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pressure: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub wind_bearing: Option<f64>,
+    pub cloud_cover: Option<f64>,
+    pub uv_index: Option<f64>,
+    pub visibility: Option<f64>,
+    pub ozone: Option<f64>,
+    /// Unix timestamp, only on daily reports
+    pub sunrise_time: Option<u64>,
+    /// Unix timestamp, only on daily reports
+    pub sunset_time: Option<u64>,
+    pub units: Option<String>,
+    pub location: Option<WeatherLocation>
+}
+
+/// Minutely, hourly or daily forecast
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeatherForecast {
+    pub summary: Option<String>,
+    pub icon: Option<String>,
+    pub data: Vec<WeatherData>,
+    pub units: Option<String>,
+    pub location: Option<WeatherLocation>
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeatherLocation {
+    pub lat: f64,
+    pub lon: f64,
+    pub address: String
+}
+
+/// Units of weather reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Units {
+    /// Celsius, meters per second, hectopascals, kilometers
+    Si,
+    /// Fahrenheit, miles per hour, millibars, miles
+    Us
+}
+
+impl Display for Units {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Si => write!(f, "si"),
+            Self::Us => write!(f, "us")
+        }
+    }
+}
+
+/// Options of weather requests
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let options = WeatherOptions::new()
+///     .units(Units::Us)
+///     .language("es");
+/// ```
+#[derive(Clone, Debug)]
+pub struct WeatherOptions {
+    units: Units,
+    language: Option<String>
+}
+
+impl WeatherOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Language of the summaries, as a two letter code like `en` or `es`
+    pub fn language(mut self, language: impl ToString) -> Self {
+        self.language = Some(language.to_string().to_lowercase());
+        self
+    }
+
+    pub(crate) fn query(&self, location: &str) -> Vec<(&'static str, String)> {
+        let mut query = vec![("q", location.to_string()), ("units", self.units.to_string())];
+        if let Some(language) = &self.language {
+            query.push(("lang", language.clone()));
+        }
+        query
+    }
+}
+
+impl Default for WeatherOptions {
+    fn default() -> Self {
+        Self {
+            units: Units::Si,
+            language: None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GisResponse {
+    pub error: bool,
+    pub code: u16,
+    /// Locations found, only the best one unless more results were requested
+    #[serde(deserialize_with = "one_or_many")]
+    pub data: Vec<GisLocation>
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GisLocation {
+    pub address: String,
+    #[serde(flatten)]
+    pub coordinates: Coordinates,
+    pub bounding_box: BoundingBox,
+    /// Kinds of the location, like `locality` or `country`
+    #[serde(rename = "type", default)]
+    pub kinds: Vec<String>,
+    /// Image of the location on a map, if requested
+    pub map: Option<String>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Coordinates {
+    #[serde(rename = "lat")]
+    pub latitude: f64,
+    #[serde(rename = "lon")]
+    pub longitude: f64
+}
+
+impl Coordinates {
+    /// Mean radius of the Earth, in meters
+    pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude
+        }
+    }
+
+    /// Great-circle distance to another point, in meters
+    pub fn distance(&self, other: Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        // Haversine formula, accurate for small distances
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * Self::EARTH_RADIUS * a.sqrt().atan2((1.0 - a).sqrt())
+    }
+
+    /// Initial bearing to another point, in degrees clockwise from north, between 0 and 360
+    pub fn bearing(&self, other: Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+}
+
+impl Display for Coordinates {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}, {}", self.latitude, self.longitude)
+    }
+}
+
+/// Area covering a location, in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64
+}
+
+impl BoundingBox {
+    pub fn center(&self) -> Coordinates {
+        // Boxes crossing the antimeridian have their west edge east of their east edge
+        let east = if self.east < self.west { self.east + 360.0 } else { self.east };
+        let longitude = (self.west + east) / 2.0;
+
+        Coordinates::new(
+            (self.south + self.north) / 2.0,
+            if longitude > 180.0 { longitude - 360.0 } else { longitude }
+        )
+    }
+
+    pub fn contains(&self, point: Coordinates) -> bool {
+        let longitude = if self.west <= self.east {
+            (self.west..=self.east).contains(&point.longitude)
+        } else {
+            point.longitude >= self.west || point.longitude <= self.east
+        };
+
+        (self.south..=self.north).contains(&point.latitude) && longitude
+    }
+}
+
+// Sent as [south, north, west, east], with the numbers usually as strings
+impl<'de> Deserialize<'de> for BoundingBox {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Number {
+            Float(f64),
+            Text(String)
+        }
+
+        let values = Vec::<Number>::deserialize(deserializer)?
+            .into_iter()
+            .map(|n| match n {
+                Number::Float(n) => Ok(n),
+                Number::Text(n) => n.trim().parse().map_err(DeError::custom)
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        match values.as_slice() {
+            [south, north, west, east] => Ok(BoundingBox {
+                south: *south,
+                north: *north,
+                west: *west,
+                east: *east
+            }),
+            _ => Err(DeError::invalid_length(values.len(), &"4 coordinates"))
+        }
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<GisLocation>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Box<GisLocation>),
+        Many(Vec<GisLocation>)
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(location) => vec![*location],
+        OneOrMany::Many(locations) => locations
+    })
+}
+
+/// Options of GIS lookups
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let options = GisOptions::new()
+///     .more(true)
+///     .map_zoom(12);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GisOptions {
+    fast: bool,
+    more: bool,
+    map_zoom: Option<u8>
+}
+
+impl GisOptions {
+    pub const MIN_ZOOM: u8 = 1;
+    pub const MAX_ZOOM: u8 = 20;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Faster but less accurate lookup
+    pub fn fast(mut self, fast: bool) -> Self {
+        self.fast = fast;
+        self
+    }
+
+    /// Returns every location found instead of only the best one
+    pub fn more(mut self, more: bool) -> Self {
+        self.more = more;
+        self
+    }
+
+    /// Includes an image of the locations on a map with the given zoom, clamped between 1 and 20
+    pub fn map_zoom(mut self, zoom: u8) -> Self {
+        self.map_zoom = Some(zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM));
+        self
+    }
+
+    pub(crate) fn query(&self, location: &str) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("q", location.to_string()),
+            ("fast", self.fast.to_string()),
+            ("more", self.more.to_string()),
+            ("include_map", self.map_zoom.is_some().to_string())
+        ];
+        if let Some(zoom) = self.map_zoom {
+            query.push(("map_zoom", zoom.to_string()));
+        }
+        query
+    }
+}
+
+/// Error of a GIS lookup, empty queries are rejected without making a request
+#[derive(Clone, Debug)]
+pub enum GisError {
+    EmptyQuery,
+    Api(KumoError)
+}
+
+impl From<KumoError> for GisError {
+    fn from(e: KumoError) -> Self {
+        GisError::Api(e)
+    }
+}
+
+impl Error for GisError {}
+
+impl Display for GisError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::EmptyQuery => write!(f, "Location cannot be empty"),
+            Self::Api(why) => write!(f, "{}", why)
+        }
+    }
+}
+
+/// Error of a weather lookup, empty locations are rejected without making a request
+#[derive(Clone, Debug)]
+pub enum WeatherError {
+    EmptyLocation,
+    Api(KumoError)
+}
+
+impl From<KumoError> for WeatherError {
+    fn from(e: KumoError) -> Self {
+        WeatherError::Api(e)
+    }
+}
+
+impl Error for WeatherError {}
+
+impl Display for WeatherError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::EmptyLocation => write!(f, "Location cannot be empty"),
+            Self::Api(why) => write!(f, "{}", why)
+        }
+    }
+}
+
+/// Addresses that can be looked up with `Kumo::geoip`, implemented for the `std::net` address types and strings
+pub trait IntoIpAddr {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError>;
+}
+
+impl IntoIpAddr for IpAddr {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        Ok(self)
+    }
+}
+
+impl IntoIpAddr for Ipv4Addr {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        Ok(IpAddr::V4(self))
+    }
+}
+
+impl IntoIpAddr for Ipv6Addr {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        Ok(IpAddr::V6(self))
+    }
+}
+
+impl IntoIpAddr for &str {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        let ip = self.trim();
+        // Accepts IPv6 addresses written as in URLs, [::1]
+        let ip = ip.strip_prefix('[').and_then(|ip| ip.strip_suffix(']')).unwrap_or(ip);
+
+        ip.parse().map_err(|_| GeoIpError::InvalidAddress(self.to_string()))
+    }
+}
+
+impl IntoIpAddr for String {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        self.as_str().into_ip_addr()
+    }
+}
+
+impl IntoIpAddr for &String {
+    fn into_ip_addr(self) -> Result<IpAddr, GeoIpError> {
+        self.as_str().into_ip_addr()
+    }
+}
+
+/// Ranges of addresses that are not publicly routable, so they have no location
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpRange {
+    Unspecified,
+    Loopback,
+    /// Private networks, IPv4 10.0.0.0/8, 172.16.0.0/12 and 192.168.0.0/16 or IPv6 unique local addresses
+    Private,
+    /// Carrier-grade NAT, 100.64.0.0/10
+    Shared,
+    LinkLocal,
+    Multicast,
+    Broadcast,
+    Documentation,
+    /// Other ranges reserved by the IANA, like benchmarking or future use
+    Reserved
+}
+
+impl IpRange {
+    /// Gets the special range an address belongs to, None if it is a public address
+    pub fn of(ip: IpAddr) -> Option<IpRange> {
+        match ip {
+            IpAddr::V4(ip) => Self::of_v4(ip),
+            IpAddr::V6(ip) => Self::of_v6(ip)
+        }
+    }
+
+    fn of_v4(ip: Ipv4Addr) -> Option<IpRange> {
+        let [a, b, c, _] = ip.octets();
+
+        if ip.is_unspecified() {
+            Some(IpRange::Unspecified)
+        } else if ip.is_loopback() {
+            Some(IpRange::Loopback)
+        } else if ip.is_private() {
+            Some(IpRange::Private)
+        } else if a == 100 && (64..128).contains(&b) {
+            Some(IpRange::Shared)
+        } else if ip.is_link_local() {
+            Some(IpRange::LinkLocal)
+        } else if ip.is_broadcast() {
+            Some(IpRange::Broadcast)
+        } else if ip.is_multicast() {
+            Some(IpRange::Multicast)
+        } else if ip.is_documentation() {
+            Some(IpRange::Documentation)
+        } else if a == 0 || a >= 240 || (a, b, c) == (192, 0, 0) || a == 198 && (b == 18 || b == 19) {
+            Some(IpRange::Reserved)
+        } else {
+            None
+        }
+    }
+
+    fn of_v6(ip: Ipv6Addr) -> Option<IpRange> {
+        if let Some(ip) = ip.to_ipv4_mapped() {
+            return Self::of_v4(ip);
+        }
+
+        let segments = ip.segments();
+        if ip.is_unspecified() {
+            Some(IpRange::Unspecified)
+        } else if ip.is_loopback() {
+            Some(IpRange::Loopback)
+        } else if segments[0] & 0xfe00 == 0xfc00 {
+            Some(IpRange::Private)
+        } else if segments[0] & 0xffc0 == 0xfe80 {
+            Some(IpRange::LinkLocal)
+        } else if ip.is_multicast() {
+            Some(IpRange::Multicast)
+        } else if segments[0] == 0x2001 && segments[1] == 0xdb8 {
+            Some(IpRange::Documentation)
+        } else if segments[0] & 0xe000 != 0x2000 {
+            // Only 2000::/3 is allocated for global unicast
+            Some(IpRange::Reserved)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for IpRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Unspecified => write!(f, "unspecified"),
+            Self::Loopback => write!(f, "loopback"),
+            Self::Private => write!(f, "private"),
+            Self::Shared => write!(f, "shared"),
+            Self::LinkLocal => write!(f, "link local"),
+            Self::Multicast => write!(f, "multicast"),
+            Self::Broadcast => write!(f, "broadcast"),
+            Self::Documentation => write!(f, "documentation"),
+            Self::Reserved => write!(f, "reserved")
+        }
+    }
+}
+
+/// Error of a GeoIP lookup, addresses that can't be located are rejected without making a request
+#[derive(Clone, Debug)]
+pub enum GeoIpError {
+    InvalidAddress(String),
+    NotPublic(IpAddr, IpRange),
+    Api(KumoError)
+}
+
+impl GeoIpError {
+    /// Checks that an address is public, so it can be located
+    pub fn check(ip: IpAddr) -> Result<IpAddr, GeoIpError> {
+        match IpRange::of(ip) {
+            Some(range) => Err(GeoIpError::NotPublic(ip, range)),
+            None => Ok(ip)
+        }
+    }
+}
+
+impl From<KumoError> for GeoIpError {
+    fn from(e: KumoError) -> Self {
+        GeoIpError::Api(e)
+    }
+}
+
+impl Error for GeoIpError {}
+
+impl Display for GeoIpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidAddress(ip) => write!(f, "{:?} is not a valid IP address", ip),
+            Self::NotPublic(ip, range) => write!(f, "{} is a {} address", ip, range),
+            Self::Api(why) => write!(f, "{}", why)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(ip: &str) -> Option<IpRange> {
+        IpRange::of(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn special_ranges() {
+        let cases = [
+            ("0.0.0.0", Some(IpRange::Unspecified)),
+            ("127.0.0.1", Some(IpRange::Loopback)),
+            ("10.1.2.3", Some(IpRange::Private)),
+            ("172.16.0.1", Some(IpRange::Private)),
+            ("172.31.255.255", Some(IpRange::Private)),
+            ("192.168.1.1", Some(IpRange::Private)),
+            ("100.64.0.1", Some(IpRange::Shared)),
+            ("100.127.255.255", Some(IpRange::Shared)),
+            ("169.254.10.20", Some(IpRange::LinkLocal)),
+            ("224.0.0.251", Some(IpRange::Multicast)),
+            ("255.255.255.255", Some(IpRange::Broadcast)),
+            ("192.0.2.1", Some(IpRange::Documentation)),
+            ("203.0.113.7", Some(IpRange::Documentation)),
+            ("198.18.0.1", Some(IpRange::Reserved)),
+            ("240.0.0.1", Some(IpRange::Reserved)),
+            ("::", Some(IpRange::Unspecified)),
+            ("::1", Some(IpRange::Loopback)),
+            ("fd12:3456:789a::1", Some(IpRange::Private)),
+            ("fe80::1ff:fe23:4567:890a", Some(IpRange::LinkLocal)),
+            ("ff02::1", Some(IpRange::Multicast)),
+            ("2001:db8::1", Some(IpRange::Documentation)),
+            ("::ffff:192.168.0.1", Some(IpRange::Private)),
+            ("4000::1", Some(IpRange::Reserved))
+        ];
+
+        for (ip, expected) in cases {
+            assert_eq!(range(ip), expected, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["8.8.8.8", "1.1.1.1", "100.63.255.255", "100.128.0.0", "172.32.0.1", "2606:4700:4700::1111", "::ffff:8.8.4.4"] {
+            assert_eq!(range(ip), None, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn geoip_inputs() {
+        assert_eq!(" [2606:4700:4700::1111] ".into_ip_addr().ok(), Some("2606:4700:4700::1111".parse().unwrap()));
+        assert!(GeoIpError::check("8.8.8.8".into_ip_addr().unwrap()).is_ok());
+        assert!(matches!("192.168.1.300".into_ip_addr(), Err(GeoIpError::InvalidAddress(ip)) if ip == "192.168.1.300"));
+        assert!(matches!(GeoIpError::check(Ipv4Addr::LOCALHOST.into()), Err(GeoIpError::NotPublic(_, IpRange::Loopback))));
+    }
+
+    const MADRID: Coordinates = Coordinates { latitude: 40.4167754, longitude: -3.7037902 };
+    const PARIS: Coordinates = Coordinates { latitude: 48.856614, longitude: 2.3522219 };
+
+    #[test]
+    fn gis_single_location() {
+        let response: GisResponse = serde_json::from_str(r#"{
+            "error": false,
+            "code": 200,
+            "data": {
+                "address": "Madrid, Spain",
+                "lat": 40.4167754,
+                "lon": -3.7037902,
+                "bounding_box": ["40.3120639", "40.5638447", "-3.8889539", "-3.5179163"],
+                "type": ["locality", "political"],
+                "map": null
+            }
+        }"#).unwrap();
+
+        assert_eq!(response.data.len(), 1);
+        let madrid = &response.data[0];
+        assert_eq!(madrid.address, "Madrid, Spain");
+        assert_eq!(madrid.coordinates, MADRID);
+        assert_eq!(madrid.kinds, vec!["locality", "political"]);
+        assert_eq!(madrid.bounding_box, BoundingBox { south: 40.3120639, north: 40.5638447, west: -3.8889539, east: -3.5179163 });
+        assert!(madrid.bounding_box.contains(madrid.coordinates));
+        assert!(!madrid.bounding_box.contains(PARIS));
+    }
+
+    #[test]
+    fn gis_many_locations() {
+        let response: GisResponse = serde_json::from_str(r#"{
+            "error": false,
+            "code": 200,
+            "data": [
+                {"address": "Paris, France", "lat": 48.856614, "lon": 2.3522219, "bounding_box": [48.815573, 48.9021449, 2.224199, 2.4699208]},
+                {"address": "Paris, TX, USA", "lat": 33.6609389, "lon": -95.555513, "bounding_box": [" 33.6087 ", "33.7391", "-95.6279", "-95.4695"], "map": "https://maps.example/paris.png"}
+            ]
+        }"#).unwrap();
+
+        let addresses = response.data.iter().map(|l| l.address.as_str()).collect::<Vec<_>>();
+        assert_eq!(addresses, vec!["Paris, France", "Paris, TX, USA"]);
+        assert!(response.data[0].kinds.is_empty());
+        assert_eq!(response.data[1].bounding_box.south, 33.6087);
+        assert_eq!(response.data[1].map.as_deref(), Some("https://maps.example/paris.png"));
+    }
+
+    #[test]
+    fn invalid_bounding_boxes() {
+        assert!(serde_json::from_str::<BoundingBox>(r#"["1", "2", "3"]"#).is_err());
+        assert!(serde_json::from_str::<BoundingBox>(r#"["1", "2", "3", "east"]"#).is_err());
+        assert!(serde_json::from_str::<BoundingBox>(r#"{"south": 1}"#).is_err());
+    }
+
+    #[test]
+    fn bounding_box_across_antimeridian() {
+        let fiji = BoundingBox { south: -21.0, north: -12.0, west: 177.0, east: -178.0 };
+
+        assert_eq!(fiji.center(), Coordinates::new(-16.5, 179.5));
+        assert!(fiji.contains(Coordinates::new(-17.7, 178.0)));
+        assert!(fiji.contains(Coordinates::new(-16.0, -179.0)));
+        assert!(!fiji.contains(Coordinates::new(-16.0, 0.0)));
+    }
+
+    #[test]
+    fn distance_and_bearing() {
+        let distance = MADRID.distance(PARIS) / 1000.0;
+        assert!((distance - 1053.0).abs() < 1.0, "{}", distance);
+        assert!((PARIS.distance(MADRID) / 1000.0 - distance).abs() < 1e-6);
+        assert_eq!(MADRID.distance(MADRID), 0.0);
+
+        let bearing = MADRID.bearing(PARIS);
+        assert!((bearing - 25.0).abs() < 1.0, "{}", bearing);
+        assert!((PARIS.bearing(MADRID) - 209.0).abs() < 1.0, "{}", PARIS.bearing(MADRID));
+        assert_eq!(Coordinates::new(0.0, 0.0).bearing(Coordinates::new(-1.0, 0.0)), 180.0);
+    }
+
+    #[test]
+    fn gis_options() {
+        let query = |options: GisOptions| options.query("Madrid")
+            .into_iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        assert_eq!(query(GisOptions::new()), "q=Madrid&fast=false&more=false&include_map=false");
+        assert_eq!(query(GisOptions::new().fast(true).more(true).map_zoom(12)), "q=Madrid&fast=true&more=true&include_map=true&map_zoom=12");
+        assert_eq!(query(GisOptions::new().map_zoom(0)), "q=Madrid&fast=false&more=false&include_map=true&map_zoom=1");
+        assert_eq!(query(GisOptions::new().map_zoom(u8::MAX)), "q=Madrid&fast=false&more=false&include_map=true&map_zoom=20");
+    }
+
+    #[test]
+    fn weather_currently() {
+        let response: WeatherResponse<WeatherData> = serde_json::from_str(r#"{
+            "error": false,
+            "status": 200,
+            "data": {
+                "time": 1600000000,
+                "summary": "Clear",
+                "icon": "clear-day",
+                "icon_url": "https://cdn.ksoft.si/images/weather/clear-day.png",
+                "precipIntensity": 0,
+                "precipProbability": 0,
+                "temperature": 27.43,
+                "apparentTemperature": 27.1,
+                "dewPoint": 8.76,
+                "humidity": 0.31,
+                "pressure": 1015.2,
+                "windSpeed": 2.81,
+                "windGust": 5.37,
+                "windBearing": 237,
+                "cloudCover": 0.02,
+                "uvIndex": 6,
+                "visibility": 16.093,
+                "ozone": 290.1,
+                "units": "si",
+                "location": {"lat": 40.4167754, "lon": -3.7037902, "address": "Madrid, Spain"}
+            }
+        }"#).unwrap();
+
+        assert_eq!(response.code, 200);
+        let weather = response.data;
+        assert_eq!(weather.time, 1600000000);
+        assert_eq!(weather.summary.as_deref(), Some("Clear"));
+        assert_eq!(weather.icon_url.as_deref(), Some("https://cdn.ksoft.si/images/weather/clear-day.png"));
+        assert_eq!(weather.temperature, Some(27.43));
+        assert_eq!(weather.wind_bearing, Some(237.0));
+        assert_eq!(weather.precip_type, None);
+        assert_eq!(weather.temperature_high, None);
+        assert_eq!(weather.location.map(|l| l.address), Some("Madrid, Spain".to_string()));
+    }
+
+    #[test]
+    fn weather_hourly() {
+        let response: WeatherResponse<WeatherForecast> = serde_json::from_str(r#"{
+            "error": false,
+            "code": 200,
+            "data": {
+                "summary": "Light rain starting tomorrow afternoon.",
+                "icon": "rain",
+                "data": [
+                    {"time": 1600002000, "summary": "Clear", "temperature": 26.9, "precipProbability": 0.01},
+                    {"time": 1600005600, "summary": "Light Rain", "temperature": 18.2, "precipType": "rain", "precipIntensity": 0.61}
+                ],
+                "units": "si",
+                "location": {"lat": 40.4167754, "lon": -3.7037902, "address": "Madrid, Spain"}
+            }
+        }"#).unwrap();
+
+        let forecast = response.data;
+        assert_eq!(forecast.icon.as_deref(), Some("rain"));
+        assert_eq!(forecast.data.iter().map(|h| h.time).collect::<Vec<_>>(), vec![1600002000, 1600005600]);
+        assert_eq!(forecast.data[1].precip_type.as_deref(), Some("rain"));
+        assert_eq!(forecast.data[1].precip_intensity, Some(0.61));
+        assert_eq!(forecast.data[0].units, None);
+    }
+
+    #[test]
+    fn weather_daily() {
+        let response: WeatherResponse<WeatherForecast> = serde_json::from_str(r#"{
+            "error": false,
+            "code": 200,
+            "data": {
+                "summary": null,
+                "data": [{
+                    "time": 1599948000,
+                    "summary": "Clear throughout the day.",
+                    "sunriseTime": 1599975235,
+                    "sunsetTime": 1600020900,
+                    "temperatureHigh": 31.55,
+                    "temperatureLow": 16.01,
+                    "uvIndex": 7
+                }]
+            }
+        }"#).unwrap();
+
+        let day = &response.data.data[0];
+        assert_eq!(response.data.summary, None);
+        assert!(response.data.location.is_none());
+        assert_eq!((day.sunrise_time, day.sunset_time), (Some(1599975235), Some(1600020900)));
+        assert_eq!((day.temperature_high, day.temperature_low), (Some(31.55), Some(16.01)));
+        assert_eq!(day.temperature, None);
+    }
+
+    #[test]
+    fn weather_options() {
+        let query = |options: WeatherOptions| options.query("Madrid")
+            .into_iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        assert_eq!(query(WeatherOptions::new()), "q=Madrid&units=si");
+        assert_eq!(query(WeatherOptions::new().units(Units::Us)), "q=Madrid&units=us");
+        assert_eq!(query(WeatherOptions::new().units(Units::Us).language("ES")), "q=Madrid&units=us&lang=es");
+        assert_eq!(query(WeatherOptions::default().language("en").units(Units::Si)), "q=Madrid&units=si&lang=en");
+    }
+
+    #[test]
+    fn geoip_links() {
+        let response: GeoIPResponse = serde_json::from_str(r#"{
+            "error": false,
+            "code": 200,
+            "data": {
+                "city": "Mountain View",
+                "continent_code": "NA",
+                "continent_name": "North America",
+                "country_code": "US",
+                "country_name": "United States",
+                "dma_code": "807",
+                "latitude": 37.386,
+                "longitude": -122.0838,
+                "postal_code": "94035",
+                "region": "CA",
+                "time_zone": "America/Los_Angeles",
+                "apis": {
+                    "weather": "/kumo/weather/currently?q=37.386,-122.0838",
+                    "gis": "/kumo/gis?q=Mountain+View",
+                    "openstreetmap": "https://www.openstreetmap.org/?mlat=37.386&mlon=-122.0838",
+                    "googlemaps": "https://www.google.com/maps/search/?api=1&query=37.386,-122.0838"
+                }
+            }
+        }"#).unwrap();
+
+        let apis = response.data.apis;
+        assert_eq!(apis.weather.map(String::from).as_deref(), Some("https://api.ksoft.si/kumo/weather/currently?q=37.386,-122.0838"));
+        assert_eq!(apis.gis.map(String::from).as_deref(), Some("https://api.ksoft.si/kumo/gis?q=Mountain+View"));
+        assert_eq!(apis.open_street_map.map(|u| u.host_str().map(String::from)), Some(Some("www.openstreetmap.org".to_string())));
+        assert!(apis.google_maps.is_some());
+    }
+
+    #[test]
+    fn malformed_geoip_links() {
+        let apis: GeoIPResponseApis = serde_json::from_str(r#"{
+            "weather": "/kumo/weather/currently?q=37.386,-122.0838",
+            "gis": null,
+            "openstreetmap": "https://www.openstreetmap.org:99999/?mlat=37.386",
+            "googlemaps": "not a link"
+        }"#).unwrap();
+
+        assert!(apis.weather.is_some());
+        assert_eq!(apis.gis, None);
+        assert_eq!(apis.open_street_map, None);
+        assert_eq!(apis.google_maps, None);
+
+        let apis: GeoIPResponseApis = serde_json::from_str("{}").unwrap();
+        assert!(apis.weather.is_none() && apis.open_street_map.is_none());
+    }
+}
+
@@ -1,4 +1,5 @@
 pub mod bans;
+pub mod currency;
 mod error;
 pub mod images;
 pub mod kumo;
@@ -1,4 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet}
+};
+use crate::{
+    prelude::{ProviderType, RecommendType},
+    utils::{normalize, similarity}
+};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Lyrics {
@@ -26,6 +34,168 @@ pub struct LyricsData {
     pub url: String
 }
 
+/// Lyrics results fetched by default by a [`LyricsQuery`](LyricsQuery)
+pub const DEFAULT_LYRICS_LIMIT: u32 = 10;
+
+/// Words usually added to video titles that are not part of the song title
+const TITLE_NOISE: &[&str] = &[
+    "official video", "official music video", "official audio", "official lyric video", "lyric video",
+    "lyrics", "audio", "video", "hd", "hq", "4k", "remastered", "visualizer", "explicit"
+];
+
+/// Lyrics search with relevance filtering
+///
+/// Queries in the form `Artist - Title`, as most video titles, are split, so results can be
+/// fuzzy matched against the artist and title
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let query = LyricsQuery::new("Luis Fonsi - Despacito (Official Video) ft. Daddy Yankee")
+///     .limit(5)
+///     .min_search_score(10.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LyricsQuery {
+    pub(crate) query: String,
+    pub(crate) text_only: bool,
+    pub(crate) limit: u32,
+    min_search_score: Option<f64>,
+    artist: Option<String>,
+    title: String
+}
+
+impl LyricsQuery {
+    pub fn new(query: impl ToString) -> Self {
+        let unbracketed = strip_brackets(&query.to_string());
+        let separator = [" - ", " – ", " — "].iter().find_map(|separator| unbracketed.split_once(separator));
+        let (artist, title) = match separator {
+            Some((artist, title)) => (Some(normalize(strip_featuring(artist))), normalize(&clean_title(title))),
+            None => (None, normalize(&clean_title(&unbracketed)))
+        };
+
+        Self {
+            query: match &artist {
+                Some(artist) => format!("{} {}", artist, title),
+                None => title.clone()
+            },
+            text_only: false,
+            limit: DEFAULT_LYRICS_LIMIT,
+            min_search_score: None,
+            artist,
+            title
+        }
+    }
+
+    pub fn text_only(mut self, text_only: bool) -> Self {
+        self.text_only = text_only;
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the minimum `search_score` of a result to be kept
+    pub fn min_search_score(mut self, score: f64) -> Self {
+        self.min_search_score = Some(score);
+        self
+    }
+
+    /// Query sent to the server, without the noise of video titles
+    pub fn search_text(&self) -> &str {
+        &self.query
+    }
+
+    /// Keeps the results over the minimum search score, most relevant first
+    ///
+    /// Relevance is relative to every given result, as in [`best_match`](Self::best_match), so the
+    /// first result is always the best match
+    pub fn filter(&self, results: Vec<LyricsData>) -> Vec<LyricsData> {
+        let relevance = results.iter().map(|l| self.relevance(l, &results)).collect::<Vec<_>>();
+        let mut ranked = results.into_iter()
+            .zip(relevance)
+            .filter(|(l, _)| self.min_search_score.is_none_or(|min| l.search_score >= min))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked.into_iter().map(|(l, _)| l).collect()
+    }
+
+    /// Gets the most relevant result over the minimum search score
+    pub fn best_match<'a>(&self, results: &'a [LyricsData]) -> Option<&'a LyricsData> {
+        results.iter()
+            .filter(|l| self.min_search_score.is_none_or(|min| l.search_score >= min))
+            .map(|l| (l, self.relevance(l, results)))
+            .fold(None, |best: Option<(&LyricsData, f64)>, (l, relevance)| match best {
+                Some((_, best_relevance)) if best_relevance >= relevance => best,
+                _ => Some((l, relevance))
+            })
+            .map(|(l, _)| l)
+    }
+
+    /// Relevance from 0 to 1, weighing the search score and popularity relative to the other
+    /// results and how similar the artist and title are to the query
+    fn relevance(&self, lyrics: &LyricsData, results: &[LyricsData]) -> f64 {
+        let max_score = results.iter().map(|l| l.search_score).fold(0.0, f64::max);
+        let max_popularity = results.iter().map(|l| l.popularity).max().unwrap_or(0);
+
+        let score = if max_score > 0.0 { lyrics.search_score / max_score } else { 0.0 };
+        let popularity = if max_popularity > 0 { lyrics.popularity as f64 / max_popularity as f64 } else { 0.0 };
+        let title = similarity(&self.title, &normalize(&clean_title(&lyrics.name)));
+
+        match &self.artist {
+            Some(artist) => {
+                let artist = similarity(artist, &normalize(&lyrics.artist));
+                0.3 * score + 0.2 * popularity + 0.25 * artist + 0.25 * title
+            },
+            None => 0.5 * score + 0.2 * popularity + 0.3 * title
+        }
+    }
+}
+
+/// Removes bracketed parts, featurings and the usual video title noise
+fn clean_title(title: &str) -> String {
+    let cleaned = strip_brackets(title);
+    let cleaned = cleaned.split(" | ").next().unwrap_or_default();
+    let mut cleaned = strip_featuring(cleaned).to_string();
+
+    while let Some(noise) = TITLE_NOISE.iter().find(|noise| {
+        cleaned.trim_end().to_ascii_lowercase().ends_with(&format!(" {}", noise))
+    }) {
+        let len = cleaned.trim_end().len() - noise.len();
+        cleaned.truncate(len);
+    }
+
+    cleaned.trim().trim_matches(|c| c == '-' || c == '–' || c == '—').trim().to_string()
+}
+
+/// Removes everything between brackets, brackets included
+fn strip_brackets(title: &str) -> String {
+    let mut stripped = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            c if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Cuts the featuring credits of a single artist or title, it must be called once split, as the
+/// credits of an artist would take the title with them
+fn strip_featuring(part: &str) -> &str {
+    let lower = part.to_ascii_lowercase();
+    let cut = [" ft.", " ft ", " feat.", " feat ", " featuring "].iter()
+        .filter_map(|marker| lower.find(marker))
+        .min()
+        .unwrap_or(part.len());
+    part[..cut].trim()
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct LyricsSingalong {
     pub lrc_timestamp: Option<String>,
@@ -126,6 +296,132 @@ pub struct YoutubeTrack {
     pub description: String,
 }
 
+/// Tracks sent in each request by default by a [`PlaylistRecommendations`](PlaylistRecommendations)
+pub const DEFAULT_RECOMMENDATIONS_CHUNK: usize = 50;
+
+/// Recommendation requests running at the same time by default
+pub const DEFAULT_RECOMMENDATIONS_CONCURRENCY: usize = 4;
+
+/// Recommendations for a whole playlist, split in multiple requests
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let query = PlaylistRecommendations::new(ProviderType::from_urls(&playlist)?)
+///     .chunk_size(25)
+///     .limit(10);
+/// ```
+#[derive(Clone)]
+pub struct PlaylistRecommendations {
+    pub(crate) provider: ProviderType,
+    pub(crate) youtube_token: Option<String>,
+    pub(crate) limit: Option<u32>,
+    pub(crate) recommend_type: Option<RecommendType>,
+    pub(crate) chunk_size: usize,
+    pub(crate) concurrency: usize
+}
+
+impl PlaylistRecommendations {
+    pub fn new(provider: ProviderType) -> Self {
+        Self {
+            provider,
+            youtube_token: None,
+            limit: None,
+            recommend_type: None,
+            chunk_size: DEFAULT_RECOMMENDATIONS_CHUNK,
+            concurrency: DEFAULT_RECOMMENDATIONS_CONCURRENCY
+        }
+    }
+
+    pub fn youtube_token(mut self, token: impl ToString) -> Self {
+        self.youtube_token = Some(token.to_string());
+        self
+    }
+
+    /// Max recommendations of each request
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn recommend_type(mut self, recommend_type: RecommendType) -> Self {
+        self.recommend_type = Some(recommend_type);
+        self
+    }
+
+    /// Max tracks sent in each request
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Max requests running at the same time, the blocking client always sends them one after another
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Builds the payload of every request
+    pub(crate) fn payloads(&self) -> Vec<MusicRecommendations> {
+        let provider = self.provider.to_string();
+
+        self.provider.clone().extract()
+            .chunks(self.chunk_size)
+            .map(|tracks| MusicRecommendations {
+                tracks: tracks.to_vec(),
+                provider: provider.clone(),
+                youtube_token: self.youtube_token.clone(),
+                limit: self.limit,
+                recommend_type: self.recommend_type.map(|t| t.to_string())
+            })
+            .collect()
+    }
+}
+
+/// Recommended track along with how many requests recommended it
+#[derive(Clone, Debug)]
+pub struct RankedTrack {
+    pub track: RecommendationTrack,
+    pub count: usize
+}
+
+impl RankedTrack {
+    /// Merges recommendations, de-duplicated by Spotify or YouTube ID, and ranks them from the most recommended,
+    /// tracks recommended as many times keep the order they were first recommended in
+    pub fn rank(tracks: impl IntoIterator<Item = RecommendationTrack>) -> Vec<RankedTrack> {
+        let mut ranked: Vec<RankedTrack> = Vec::new();
+        let mut spotify_ids: HashMap<String, usize> = HashMap::new();
+        let mut youtube_ids: HashMap<String, usize> = HashMap::new();
+
+        for track in tracks {
+            let known = spotify_ids.get(track.spotify.id.as_str())
+                .or_else(|| youtube_ids.get(track.youtube.id.as_str()))
+                .copied();
+
+            let index = match known {
+                Some(index) => {
+                    ranked[index].count += 1;
+                    index
+                },
+                None => {
+                    ranked.push(RankedTrack {
+                        track: track.clone(),
+                        count: 1
+                    });
+                    ranked.len() - 1
+                }
+            };
+
+            if !track.spotify.id.is_empty() { spotify_ids.entry(track.spotify.id).or_insert(index); }
+            if !track.youtube.id.is_empty() { youtube_ids.entry(track.youtube.id).or_insert(index); }
+        }
+
+        // Stable sort, so ties keep their first recommendation order
+        ranked.sort_by_key(|r| Reverse(r.count));
+        ranked
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Artist {
     pub id: u64,
@@ -176,3 +472,220 @@ pub struct TrackAlbum {
     pub name: String,
     pub year: u16
 }
+
+/// Artist with every album and track, as returned by `Music::discography`
+#[derive(Clone, Debug)]
+pub struct Discography {
+    pub artist: Artist,
+    pub albums: Vec<DiscographyAlbum>,
+    /// Tracks of the artist that are not part of any of its albums
+    pub other_tracks: Vec<Track>
+}
+
+#[derive(Clone, Debug)]
+pub struct DiscographyAlbum {
+    pub album: Album,
+    pub tracks: Vec<Track>
+}
+
+impl Discography {
+    /// IDs of every track of the artist and its albums, without duplicates
+    pub(crate) fn track_ids(artist: &Artist, albums: &[Album]) -> Vec<u64> {
+        let mut seen = HashSet::new();
+
+        albums.iter()
+            .flat_map(|album| &album.tracks)
+            .chain(&artist.tracks)
+            .map(|track| track.id)
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    pub(crate) fn assemble(artist: Artist, albums: Vec<Album>, mut tracks: HashMap<u64, Track>) -> Self {
+        let in_albums = albums.iter()
+            .flat_map(|album| &album.tracks)
+            .map(|track| track.id)
+            .collect::<HashSet<_>>();
+
+        let albums = albums.into_iter()
+            .map(|album| DiscographyAlbum {
+                tracks: album.tracks.iter().filter_map(|t| tracks.get(&t.id).cloned()).collect(),
+                album
+            })
+            .collect();
+
+        let other_tracks = artist.tracks.iter()
+            .filter(|t| !in_albums.contains(&t.id))
+            .filter_map(|t| tracks.remove(&t.id))
+            .collect();
+
+        Self {
+            artist,
+            albums,
+            other_tracks
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lyrics(artist: &str, name: &str, search_score: f64, popularity: u64) -> LyricsData {
+        let mut lyrics: LyricsData = serde_json::from_value(serde_json::json!({
+            "artist": "", "artist_id": 0, "album": "", "album_ids": "", "album_year": "", "name": "",
+            "lyrics": "", "search_str": "", "album_art": "", "popularity": 0, "singalong": [],
+            "meta": {
+                "spotify": {"artists": [], "track": null, "album": null},
+                "deezer": {"artists": [], "track": null, "album": null},
+                "artists": null,
+                "other": {"gain": 0.0, "bpm": 0.0}
+            },
+            "id": "", "search_score": 0.0, "url": ""
+        })).unwrap();
+        lyrics.artist = artist.to_string();
+        lyrics.name = name.to_string();
+        lyrics.search_score = search_score;
+        lyrics.popularity = popularity;
+        lyrics
+    }
+
+    fn names(results: &[LyricsData]) -> Vec<&str> {
+        results.iter().map(|l| l.name.as_str()).collect()
+    }
+
+    #[test]
+    fn video_titles() {
+        let titles = [
+            ("Despacito (Official Video)", "Despacito"),
+            ("Despacito ft. Daddy Yankee", "Despacito"),
+            ("Bohemian Rhapsody [Remastered 2011]", "Bohemian Rhapsody"),
+            ("Old Town Road (feat. Billy Ray Cyrus) [Remix] Official Audio", "Old Town Road"),
+            ("Blinding Lights | Vevo Presents", "Blinding Lights"),
+            ("Shape of You Lyric Video HD", "Shape of You"),
+            ("Smells Like Teen Spirit", "Smells Like Teen Spirit")
+        ];
+
+        for (title, cleaned) in titles.iter() {
+            assert_eq!(clean_title(title), *cleaned, "{}", title);
+        }
+    }
+
+    #[test]
+    fn search_text() {
+        let queries = [
+            ("Luis Fonsi - Despacito (Official Video) ft. Daddy Yankee", "luis fonsi despacito"),
+            ("Luis Fonsi ft. Daddy Yankee - Despacito", "luis fonsi despacito"),
+            ("Ñandú ft Ñ - Árbol", "ñandú árbol"),
+            ("Rosalía – MALAMENTE (Cap.1: Augurio)", "rosalía malamente"),
+            ("Queen — Bohemian Rhapsody (Official Video Remastered)", "queen bohemian rhapsody"),
+            ("Daft Punk - Get Lucky (Radio Edit - Audio) feat. Pharrell Williams", "daft punk get lucky"),
+            ("Despacito (Official Video)", "despacito"),
+            ("Get Lucky feat. Pharrell Williams | Daft Punk", "get lucky")
+        ];
+
+        for (query, text) in queries.iter() {
+            assert_eq!(LyricsQuery::new(query).search_text(), *text, "{}", query);
+        }
+    }
+
+    #[test]
+    fn relevance() {
+        let query = LyricsQuery::new("Luis Fonsi - Despacito (Official Video)");
+        let results = vec![
+            lyrics("Luis Fonsi", "Despacito (Remix)", 50.0, 10),
+            lyrics("Despacito Tribute Band", "Despacito Karaoke", 100.0, 2)
+        ];
+
+        let exact = query.relevance(&results[0], &results);
+        let tribute = query.relevance(&results[1], &results);
+        assert!(exact > tribute, "{} <= {}", exact, tribute);
+        assert!((0.0..=1.0).contains(&exact) && (0.0..=1.0).contains(&tribute));
+
+        let unknown = LyricsQuery::new("Despacito");
+        let perfect = vec![lyrics("Luis Fonsi", "Despacito", 10.0, 10)];
+        assert!((unknown.relevance(&perfect[0], &perfect) - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn filter_and_best_match() {
+        let query = LyricsQuery::new("Queen - Bohemian Rhapsody (Official Video Remastered)").min_search_score(10.0);
+        let results = vec![
+            lyrics("Panic! at the Disco", "Bohemian Rhapsody", 40.0, 50),
+            lyrics("Queen", "Bohemian Rhapsody - Remastered 2011", 35.0, 90),
+            lyrics("Queen", "Bohemian Rhapsody", 5.0, 100)
+        ];
+
+        let filtered = query.filter(results.clone());
+        assert_eq!(names(&filtered), vec!["Bohemian Rhapsody - Remastered 2011", "Bohemian Rhapsody"]);
+        assert_eq!(filtered[1].artist, "Panic! at the Disco");
+        assert_eq!(query.best_match(&results).map(|l| l.artist.as_str()), Some("Queen"));
+        assert_eq!(query.best_match(&results).map(|l| l.search_score), Some(35.0));
+
+        assert!(query.filter(Vec::new()).is_empty());
+        assert!(query.best_match(&[]).is_none());
+        assert!(LyricsQuery::new("Queen").min_search_score(100.0).best_match(&results).is_none());
+    }
+
+    fn recommendation(name: &str, spotify_id: &str, youtube_id: &str) -> RecommendationTrack {
+        serde_json::from_value(serde_json::json!({
+            "youtube": {"id": youtube_id, "link": "", "title": name, "thumbnail": "", "description": ""},
+            "spotify": {
+                "id": spotify_id,
+                "album": {"name": "", "album_art": "", "link": ""},
+                "artists": [],
+                "name": name,
+                "link": ""
+            },
+            "name": name
+        })).unwrap()
+    }
+
+    #[test]
+    fn rank_by_frequency() {
+        let ranked = RankedTrack::rank(vec![
+            recommendation("Under Pressure", "2fuCquhmrzHpu5xcA1ci9x", ""),
+            recommendation("Bohemian Rhapsody", "3z8h0TU7ReDPLIbEnYhWZb", "fJ9rUzIMcZQ"),
+            recommendation("Somebody to Love", "", "kijpcUv-b8M"),
+            recommendation("Bohemian Rhapsody (Remastered)", "", "fJ9rUzIMcZQ"),
+            recommendation("Under Pressure", "2fuCquhmrzHpu5xcA1ci9x", "a01QQZyl-_I"),
+            recommendation("Under Pressure", "", "a01QQZyl-_I"),
+            recommendation("Bohemian Rhapsody", "3z8h0TU7ReDPLIbEnYhWZb", ""),
+            recommendation("Untracked", "", "")
+        ]);
+
+        let ranks = ranked.iter().map(|r| (r.track.name.as_str(), r.count)).collect::<Vec<_>>();
+        assert_eq!(ranks, vec![
+            ("Under Pressure", 3),
+            ("Bohemian Rhapsody", 3),
+            ("Somebody to Love", 1),
+            ("Untracked", 1)
+        ]);
+        assert!(RankedTrack::rank(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn playlist_chunks() {
+        let ids = (0..120).map(|i| format!("track{}", i)).collect::<Vec<_>>();
+        let payloads = PlaylistRecommendations::new(ProviderType::SpotifyIDs(ids.clone()))
+            .limit(5)
+            .recommend_type(RecommendType::YoutubeIDs)
+            .payloads();
+
+        let sizes = payloads.iter().map(|p| p.tracks.len()).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![50, 50, 20]);
+        assert_eq!(payloads.iter().flat_map(|p| p.tracks.clone()).collect::<Vec<_>>(), ids);
+        assert!(payloads.iter().all(|p| p.provider == "spotify" && p.limit == Some(5)
+            && p.recommend_type.as_deref() == Some("youtube_ids") && p.youtube_token.is_none()));
+
+        let payloads = PlaylistRecommendations::new(ProviderType::YoutubeIDs(ids[..3].to_vec()))
+            .chunk_size(0)
+            .youtube_token("token")
+            .payloads();
+        assert_eq!(payloads.iter().map(|p| p.tracks.len()).collect::<Vec<_>>(), vec![1, 1, 1]);
+        assert_eq!(payloads[0].youtube_token.as_deref(), Some("token"));
+
+        assert!(PlaylistRecommendations::new(ProviderType::Youtube(Vec::new())).payloads().is_empty());
+    }
+}
+
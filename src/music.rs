@@ -1,6 +1,13 @@
 use reqwest::{Client as HttpClient};
+use std::{
+    collections::HashMap,
+    sync::Arc
+};
+use crate::cache::ResponseCache;
 use crate::{
     make_request,
+    send_request,
+    collect_all,
     endpoint,
     model::*,
     HttpResult
@@ -8,14 +15,19 @@ use crate::{
 use crate::model::music::*;
 use crate::prelude::*;
 
+/// Max lookups running at the same time by default when following artists, albums and tracks
+pub const DEFAULT_NAVIGATION_CONCURRENCY: usize = 4;
+
 pub struct Music {
-    http: HttpClient
+    http: HttpClient,
+    pub(crate) response_cache: Option<Arc<ResponseCache>>
 }
 
 impl Music {
     pub fn new(http_client: HttpClient) -> Self {
         Self {
-            http: http_client
+            http: http_client,
+            response_cache: None
         }
     }
 
@@ -39,12 +51,12 @@ impl Music {
                                  limit: u32) -> reqwest::Result<Lyrics> {
         if query.to_string().is_empty() { panic!("Query param cannot be empty") }
 
-        let response = self.http.get(endpoint("/lyrics/search").as_str())
+        let builder = self.http.get(endpoint("/lyrics/search").as_str())
             .query(&[("q", query.to_string())])
             .query(&[("text_only", text_only)])
-            .query(&[("limit", limit)])
-            .send()
-            .await?;
+            .query(&[("limit", limit)]);
+
+        let response = send_request(builder, self.response_cache.as_deref()).await?;
 
         response.json::<Lyrics>().await
     }
@@ -69,6 +81,37 @@ impl Music {
         self.advanced_lyrics(query, false, 10).await
     }
 
+    ///Searches lyrics with a query, keeping the relevant results, most relevant first
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = LyricsQuery::new("Luis Fonsi - Despacito (Official Video)").min_search_score(5.0);
+    /// if let Ok(results) = client.music.search_lyrics(&query).await {
+    ///     //do something with the results
+    /// }
+    /// ```
+    pub async fn search_lyrics(&self, query: &LyricsQuery) -> reqwest::Result<Vec<LyricsData>> {
+        let lyrics = self.advanced_lyrics(query.search_text(), query.text_only, query.limit).await?;
+
+        Ok(query.filter(lyrics.data))
+    }
+
+    ///Gets the lyrics that best match a query
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Some(lyrics)) = client.music.best_lyrics(&LyricsQuery::new(video_title)).await {
+    ///     //do something with the lyrics
+    /// }
+    /// ```
+    pub async fn best_lyrics(&self, query: &LyricsQuery) -> reqwest::Result<Option<LyricsData>> {
+        let lyrics = self.advanced_lyrics(query.search_text(), query.text_only, query.limit).await?;
+
+        Ok(query.best_match(&lyrics.data).cloned())
+    }
+
     ///Get recommendations of songs with given query specifying custom parameters
     ///
     /// **You need a premium plan to use this endpoint**
@@ -88,23 +131,55 @@ impl Music {
     ///     }
     // }
     /// ```
-    pub async fn advanced_recommendations(&self, mut provider: ProviderType, youtube_token: Option<String>, limit: Option<u32>, recommend_type: Option<String>) -> HttpResult<MusicRecommendationsResponse, MusicError>{
+    pub async fn advanced_recommendations(&self, mut provider: ProviderType, youtube_token: Option<String>, limit: Option<u32>, recommend_type: Option<RecommendType>) -> HttpResult<MusicRecommendationsResponse, MusicError>{
         let track_vec = provider.extract();
 
-        if track_vec.len() < 1 { panic!("Vector contents cannot be empty") }
+        if track_vec.is_empty() { panic!("Vector contents cannot be empty") }
 
-        let payload = MusicRecommendations {
+        self.recommend(&MusicRecommendations {
             tracks: track_vec,
             provider: provider.to_string(),
             youtube_token,
             limit,
-            recommend_type
+            recommend_type: recommend_type.map(|t| t.to_string())
+        }).await
+    }
+
+    ///Get recommendations for a whole playlist, sending its tracks in chunks
+    ///
+    /// Recommendations of every chunk are de-duplicated and ranked by how many chunks recommended them,
+    /// the first error returned by the API is returned instead
+    ///
+    /// **You need a premium plan to use this endpoint**
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let query = PlaylistRecommendations::new(ProviderType::SpotifyIDs(playlist));
+    /// if let Ok(Ok(tracks)) = client.music.playlist_recommendations(&query).await {
+    ///     for ranked in tracks {
+    ///         //do something with ranked.track
+    ///     }
+    /// }
+    /// ```
+    pub async fn playlist_recommendations(&self, query: &PlaylistRecommendations) -> HttpResult<Vec<RankedTrack>, MusicError> {
+        let payloads = query.payloads();
+
+        if payloads.is_empty() { panic!("Vector contents cannot be empty") }
+
+        let responses = match collect_all(payloads.iter().map(|p| self.recommend(p)), query.concurrency).await? {
+            Ok(responses) => responses,
+            Err(why) => return Ok(Err(why))
         };
 
+        Ok(Ok(RankedTrack::rank(responses.into_iter().flat_map(|r| r.tracks))))
+    }
+
+    async fn recommend(&self, payload: &MusicRecommendations) -> HttpResult<MusicRecommendationsResponse, MusicError> {
         let builder = self.http.post(endpoint("/music/recommendations").as_str())
-            .json(&payload);
+            .json(payload);
 
-        make_request::<MusicRecommendationsResponse, MusicError>(builder).await
+        make_request::<MusicRecommendationsResponse, MusicError>(builder, self.response_cache.as_deref()).await
     }
 
     ///Get recommendations of songs with given query
@@ -148,7 +223,7 @@ impl Music {
     pub async fn artist(&self, id: impl Into<u64>) -> HttpResult<Artist, MusicError> {
         let builder = self.http.get(endpoint(format!("/lyrics/artist/{}/", id.into())).as_str());
 
-        make_request::<Artist, MusicError>(builder).await
+        make_request::<Artist, MusicError>(builder, self.response_cache.as_deref()).await
     }
 
     /// Get album information by a given ID
@@ -169,7 +244,7 @@ impl Music {
     pub async fn album(&self, id: impl Into<u64>) -> HttpResult<Album, MusicError> {
         let builder = self.http.get(endpoint(format!("/lyrics/album/{}/", id.into())).as_str());
 
-        make_request::<Album, MusicError>(builder).await
+        make_request::<Album, MusicError>(builder, self.response_cache.as_deref()).await
     }
 
     /// Get album information by a given ID
@@ -190,6 +265,166 @@ impl Music {
     pub async fn track(&self, id: impl Into<u64>) -> HttpResult<Track, MusicError> {
         let builder = self.http.get(endpoint(format!("/lyrics/track/{}/", id.into())).as_str());
 
-        make_request::<Track, MusicError>(builder).await
+        make_request::<Track, MusicError>(builder, self.response_cache.as_deref()).await
+    }
+
+    ///Get an artist along with all its albums and tracks, running up to `concurrency` lookups at the same time
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(discography)) = client.music.advanced_discography(1398u64, 8).await {
+    ///     for album in discography.albums {
+    ///         //do something with album.album and album.tracks
+    ///     }
+    /// }
+    /// ```
+    pub async fn advanced_discography(&self, artist_id: impl Into<u64>, concurrency: usize) -> HttpResult<Discography, MusicError> {
+        let artist = match self.artist(artist_id).await? {
+            Ok(artist) => artist,
+            Err(why) => return Ok(Err(why))
+        };
+
+        let albums = match artist.advanced_fetch_albums(self, concurrency).await? {
+            Ok(albums) => albums,
+            Err(why) => return Ok(Err(why))
+        };
+
+        let ids = Discography::track_ids(&artist, &albums);
+        let tracks = match collect_all(ids.iter().map(|id| self.track(*id)), concurrency).await? {
+            Ok(tracks) => tracks,
+            Err(why) => return Ok(Err(why))
+        };
+        let tracks = ids.into_iter().zip(tracks).collect::<HashMap<_, _>>();
+
+        Ok(Ok(Discography::assemble(artist, albums, tracks)))
+    }
+
+    ///Shortcut to advanced_discography() but with default concurrency
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(discography)) = client.music.discography(1398u64).await {
+    ///     //do something with the discography
+    /// }
+    /// ```
+    pub async fn discography(&self, artist_id: impl Into<u64>) -> HttpResult<Discography, MusicError> {
+        self.advanced_discography(artist_id, DEFAULT_NAVIGATION_CONCURRENCY).await
+    }
+}
+
+impl Artist {
+    ///Get every album of the artist, running up to `concurrency` lookups at the same time
+    pub async fn advanced_fetch_albums(&self, music: &Music, concurrency: usize) -> HttpResult<Vec<Album>, MusicError> {
+        collect_all(self.albums.iter().map(|album| album.fetch(music)), concurrency).await
+    }
+
+    ///Get every album of the artist
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(artist)) = client.music.artist(1398u64).await {
+    ///     if let Ok(Ok(albums)) = artist.fetch_albums(&client.music).await {
+    ///         //do something with the albums
+    ///     }
+    /// }
+    /// ```
+    pub async fn fetch_albums(&self, music: &Music) -> HttpResult<Vec<Album>, MusicError> {
+        self.advanced_fetch_albums(music, DEFAULT_NAVIGATION_CONCURRENCY).await
+    }
+
+    ///Get every track of the artist along with its lyrics
+    pub async fn fetch_tracks(&self, music: &Music) -> HttpResult<Vec<Track>, MusicError> {
+        collect_all(self.tracks.iter().map(|track| track.fetch(music)), DEFAULT_NAVIGATION_CONCURRENCY).await
+    }
+}
+
+impl Album {
+    ///Get every track of the album along with its lyrics, running up to `concurrency` lookups at the same time
+    pub async fn advanced_fetch_tracks_with_lyrics(&self, music: &Music, concurrency: usize) -> HttpResult<Vec<Track>, MusicError> {
+        collect_all(self.tracks.iter().map(|track| track.fetch(music)), concurrency).await
+    }
+
+    ///Get every track of the album along with its lyrics
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Ok(Ok(album)) = client.music.album(88151u64).await {
+    ///     if let Ok(Ok(tracks)) = album.fetch_tracks_with_lyrics(&client.music).await {
+    ///         //do something with the tracks
+    ///     }
+    /// }
+    /// ```
+    pub async fn fetch_tracks_with_lyrics(&self, music: &Music) -> HttpResult<Vec<Track>, MusicError> {
+        self.advanced_fetch_tracks_with_lyrics(music, DEFAULT_NAVIGATION_CONCURRENCY).await
+    }
+
+    pub async fn fetch_artist(&self, music: &Music) -> HttpResult<Artist, MusicError> {
+        self.artist.fetch(music).await
+    }
+}
+
+impl Track {
+    ///Get every album the track is part of
+    pub async fn fetch_albums(&self, music: &Music) -> HttpResult<Vec<Album>, MusicError> {
+        collect_all(self.albums.iter().map(|album| album.fetch(music)), DEFAULT_NAVIGATION_CONCURRENCY).await
+    }
+
+    pub async fn fetch_artist(&self, music: &Music) -> HttpResult<Artist, MusicError> {
+        self.artist.fetch(music).await
+    }
+}
+
+impl ArtistAlbum {
+    pub async fn fetch(&self, music: &Music) -> HttpResult<Album, MusicError> {
+        music.album(self.id).await
+    }
+}
+
+impl ArtistTrack {
+    pub async fn fetch(&self, music: &Music) -> HttpResult<Track, MusicError> {
+        music.track(self.id).await
+    }
+}
+
+impl TrackAlbum {
+    pub async fn fetch(&self, music: &Music) -> HttpResult<Album, MusicError> {
+        music.album(self.id).await
+    }
+}
+
+impl AlbumArtist {
+    pub async fn fetch(&self, music: &Music) -> HttpResult<Artist, MusicError> {
+        music.artist(self.id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>(_: T) {}
+
+    // Never polled, only checks the futures can be spawned on a multi-threaded runtime
+    #[allow(dead_code)]
+    fn navigation_futures_are_send(music: &Music, artist: &Artist, album: &Album, track: &Track) {
+        assert_send(music.advanced_discography(1u64, 2));
+        assert_send(music.discography(1u64));
+        assert_send(artist.advanced_fetch_albums(music, 2));
+        assert_send(artist.fetch_albums(music));
+        assert_send(artist.fetch_tracks(music));
+        assert_send(album.advanced_fetch_tracks_with_lyrics(music, 2));
+        assert_send(album.fetch_tracks_with_lyrics(music));
+        assert_send(album.fetch_artist(music));
+        assert_send(track.fetch_albums(music));
+        assert_send(track.fetch_artist(music));
+    }
+
+    #[allow(dead_code)]
+    fn playlist_recommendations_is_send(music: &Music, query: &PlaylistRecommendations) {
+        assert_send(music.playlist_recommendations(query));
     }
 }
@@ -0,0 +1,260 @@
+//! Export recommendations as playlists that can be imported into other players
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! if let Ok(Ok(recommendations)) = client.music.recommendations(provider).await {
+//!     let playlist = recommendations.export(PlaylistFormat::M3u, LinkSource::Youtube);
+//!     std::fs::write(format!("radio.{}", PlaylistFormat::M3u.extension()), playlist)?;
+//! }
+//! ```
+use std::fmt::Write;
+use crate::model::music::{MusicRecommendationsResponse, RecommendationTrack};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// Extended M3U, encoded in UTF-8 (M3U8)
+    M3u,
+    Xspf,
+    /// One Spotify URI per line, it can be pasted into a Spotify playlist
+    SpotifyUris
+}
+
+impl PlaylistFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "m3u8",
+            PlaylistFormat::Xspf => "xspf",
+            PlaylistFormat::SpotifyUris => "txt"
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "audio/x-mpegurl",
+            PlaylistFormat::Xspf => "application/xspf+xml",
+            PlaylistFormat::SpotifyUris => "text/plain"
+        }
+    }
+}
+
+/// Links written in M3U and XSPF playlists, the other link is used if a track has none
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkSource {
+    Youtube,
+    Spotify
+}
+
+/// Writes tracks in the given format, see [`to_m3u`](to_m3u), [`to_xspf`](to_xspf) and [`to_spotify_uris`](to_spotify_uris)
+pub fn export<'a>(tracks: impl IntoIterator<Item = &'a RecommendationTrack>, format: PlaylistFormat, links: LinkSource) -> String {
+    match format {
+        PlaylistFormat::M3u => to_m3u(tracks, links),
+        PlaylistFormat::Xspf => to_xspf(tracks, links),
+        PlaylistFormat::SpotifyUris => to_spotify_uris(tracks)
+    }
+}
+
+/// Writes an extended M3U playlist, tracks without links are skipped
+pub fn to_m3u<'a>(tracks: impl IntoIterator<Item = &'a RecommendationTrack>, links: LinkSource) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+
+    for track in tracks {
+        let link = match link(track, links) {
+            Some(link) => link,
+            None => continue
+        };
+
+        // Durations are unknown, -1 lets players read them from the track
+        let _ = writeln!(m3u, "#EXTINF:-1,{}", single_line(&display_name(track)));
+        if !track.spotify.album.album_art.is_empty() {
+            let _ = writeln!(m3u, "#EXTIMG:{}", track.spotify.album.album_art);
+        }
+        let _ = writeln!(m3u, "{}", link);
+    }
+
+    m3u
+}
+
+/// Writes an XSPF playlist, tracks without links are skipped
+pub fn to_xspf<'a>(tracks: impl IntoIterator<Item = &'a RecommendationTrack>, links: LinkSource) -> String {
+    let mut xspf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n");
+
+    for track in tracks {
+        let location = match link(track, links) {
+            Some(link) => link,
+            None => continue
+        };
+
+        xspf.push_str("    <track>\n");
+        element(&mut xspf, "location", location);
+        element(&mut xspf, "title", title(track));
+        element(&mut xspf, "creator", &artists(track));
+        element(&mut xspf, "album", &track.spotify.album.name);
+        element(&mut xspf, "image", &track.spotify.album.album_art);
+        if !track.spotify.id.is_empty() {
+            element(&mut xspf, "identifier", &spotify_uri(track));
+        }
+        xspf.push_str("    </track>\n");
+    }
+
+    xspf.push_str("  </trackList>\n</playlist>\n");
+    xspf
+}
+
+/// Writes one Spotify URI per line, tracks without Spotify ID are skipped
+pub fn to_spotify_uris<'a>(tracks: impl IntoIterator<Item = &'a RecommendationTrack>) -> String {
+    tracks.into_iter()
+        .filter(|track| !track.spotify.id.is_empty())
+        .map(|track| spotify_uri(track) + "\n")
+        .collect()
+}
+
+impl MusicRecommendationsResponse {
+    /// Writes the recommended tracks in the given format
+    pub fn export(&self, format: PlaylistFormat, links: LinkSource) -> String {
+        export(&self.tracks, format, links)
+    }
+}
+
+fn link(track: &RecommendationTrack, links: LinkSource) -> Option<&str> {
+    let (youtube, spotify) = (track.youtube.link.as_str(), track.spotify.link.as_str());
+    let (first, second) = match links {
+        LinkSource::Youtube => (youtube, spotify),
+        LinkSource::Spotify => (spotify, youtube)
+    };
+
+    if !first.is_empty() {
+        Some(first)
+    } else if !second.is_empty() {
+        Some(second)
+    } else {
+        None
+    }
+}
+
+fn spotify_uri(track: &RecommendationTrack) -> String {
+    format!("spotify:track:{}", track.spotify.id)
+}
+
+fn title(track: &RecommendationTrack) -> &str {
+    if track.spotify.name.is_empty() { &track.name } else { &track.spotify.name }
+}
+
+fn artists(track: &RecommendationTrack) -> String {
+    track.spotify.artists.iter()
+        .map(|artist| artist.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn display_name(track: &RecommendationTrack) -> String {
+    match artists(track) {
+        artists if artists.is_empty() => title(track).to_string(),
+        artists => format!("{} - {}", artists, title(track))
+    }
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn element(xml: &mut String, name: &str, value: &str) {
+    if value.is_empty() { return }
+
+    let _ = writeln!(xml, "      <{0}>{1}</{0}>", name, escape_xml(value));
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(name: &str, artists: &[&str], spotify_id: &str, youtube_link: &str) -> RecommendationTrack {
+        serde_json::from_value(serde_json::json!({
+            "youtube": {
+                "id": "",
+                "link": youtube_link,
+                "title": name,
+                "thumbnail": "",
+                "description": ""
+            },
+            "spotify": {
+                "id": spotify_id,
+                "album": {"name": "", "album_art": "", "link": ""},
+                "artists": artists.iter().map(|name| serde_json::json!({"name": name, "link": ""})).collect::<Vec<_>>(),
+                "name": name,
+                "link": if spotify_id.is_empty() { String::new() } else { format!("https://open.spotify.com/track/{}", spotify_id) }
+            },
+            "name": name
+        })).unwrap()
+    }
+
+    fn tracks() -> Vec<RecommendationTrack> {
+        let mut with_album = track("Don't Stop Me Now", &["Queen"], "7hQJA50XrCWABAu5v6QZ4i", "https://youtu.be/HgzGwKwLmgM");
+        with_album.spotify.album.name = "Jazz".to_string();
+        with_album.spotify.album.album_art = "https://i.scdn.co/image/jazz.jpg".to_string();
+
+        vec![
+            with_album,
+            track("Simon & Garfunkel <Live>", &["Simon \"Paul\"", "Art"], "", "https://youtu.be/4zLfCnGVeL4"),
+            track("Multi\nline", &[], "3n3Ppam7vgaVa1iaRUc9Lp", ""),
+            track("No links", &["Nobody"], "", "")
+        ]
+    }
+
+    #[test]
+    fn m3u() {
+        assert_eq!(to_m3u(&tracks(), LinkSource::Youtube), "#EXTM3U\n\
+            #EXTINF:-1,Queen - Don't Stop Me Now\n\
+            #EXTIMG:https://i.scdn.co/image/jazz.jpg\n\
+            https://youtu.be/HgzGwKwLmgM\n\
+            #EXTINF:-1,Simon \"Paul\", Art - Simon & Garfunkel <Live>\n\
+            https://youtu.be/4zLfCnGVeL4\n\
+            #EXTINF:-1,Multi line\n\
+            https://open.spotify.com/track/3n3Ppam7vgaVa1iaRUc9Lp\n");
+
+        assert_eq!(to_m3u(&tracks()[..1], LinkSource::Spotify), "#EXTM3U\n\
+            #EXTINF:-1,Queen - Don't Stop Me Now\n\
+            #EXTIMG:https://i.scdn.co/image/jazz.jpg\n\
+            https://open.spotify.com/track/7hQJA50XrCWABAu5v6QZ4i\n");
+    }
+
+    #[test]
+    fn xspf() {
+        assert_eq!(to_xspf(&tracks()[..2], LinkSource::Youtube), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n    <track>\n      \
+            <location>https://youtu.be/HgzGwKwLmgM</location>\n      \
+            <title>Don&apos;t Stop Me Now</title>\n      \
+            <creator>Queen</creator>\n      \
+            <album>Jazz</album>\n      \
+            <image>https://i.scdn.co/image/jazz.jpg</image>\n      \
+            <identifier>spotify:track:7hQJA50XrCWABAu5v6QZ4i</identifier>\n    \
+            </track>\n    <track>\n      \
+            <location>https://youtu.be/4zLfCnGVeL4</location>\n      \
+            <title>Simon &amp; Garfunkel &lt;Live&gt;</title>\n      \
+            <creator>Simon &quot;Paul&quot;, Art</creator>\n    \
+            </track>\n  </trackList>\n</playlist>\n");
+
+        assert_eq!(escape_xml("<a href=\"x\">Tom & Jerry's</a>"), "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;");
+    }
+
+    #[test]
+    fn spotify_uris() {
+        assert_eq!(to_spotify_uris(&tracks()), "spotify:track:7hQJA50XrCWABAu5v6QZ4i\nspotify:track:3n3Ppam7vgaVa1iaRUc9Lp\n");
+        assert_eq!(export(&tracks()[1..2], PlaylistFormat::SpotifyUris, LinkSource::Youtube), "");
+    }
+}
@@ -1,4 +1,8 @@
-use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult}
+};
+use reqwest::Url;
 
 #[cfg(feature = "default")]
 pub use async_trait::async_trait;
@@ -23,6 +27,61 @@ impl Display for ProviderType {
 }
 
 impl ProviderType {
+    /// Sorts YouTube and Spotify track URLs, Spotify URIs and bare IDs into a provider
+    ///
+    /// YouTube inputs become [`YoutubeIDs`](ProviderType::YoutubeIDs) and Spotify inputs [`SpotifyIDs`](ProviderType::SpotifyIDs),
+    /// the first recognized input decides the provider. Every input that is not a track of that provider is
+    /// returned in the error with the reason it was rejected
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let provider = ProviderType::from_urls(&[
+    ///     "https://www.youtube.com/watch?v=kJQP7kiw5Fk",
+    ///     "https://youtu.be/kJQP7kiw5Fk"
+    /// ])?;
+    /// ```
+    pub fn from_urls<I, S>(inputs: I) -> Result<Self, ProviderError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>
+    {
+        let mut provider: Option<TrackSource> = None;
+        let mut ids = Vec::new();
+        let mut rejected = Vec::new();
+
+        for input in inputs {
+            let input = input.as_ref();
+            let reason = match TrackSource::parse(input) {
+                Ok((source, id)) => match provider {
+                    Some(expected) if expected != source => RejectReason::MixedProviders {
+                        expected: expected.name(),
+                        found: source.name()
+                    },
+                    _ => {
+                        provider = Some(source);
+                        ids.push(id);
+                        continue;
+                    }
+                },
+                Err(reason) => reason
+            };
+
+            rejected.push(RejectedInput {
+                input: input.to_string(),
+                reason
+            });
+        }
+
+        if !rejected.is_empty() { return Err(ProviderError::Rejected(rejected)) }
+
+        match provider {
+            Some(TrackSource::Youtube) => Ok(ProviderType::YoutubeIDs(ids)),
+            Some(TrackSource::Spotify) => Ok(ProviderType::SpotifyIDs(ids)),
+            None => Err(ProviderError::Empty)
+        }
+    }
+
     /// Extracts the actual vector inside provider without copying it
     pub(crate) fn extract(&mut self) -> Vec<String> {
         use std::mem::take;
@@ -36,6 +95,174 @@ impl ProviderType {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TrackSource {
+    Youtube,
+    Spotify
+}
+
+impl TrackSource {
+    const YOUTUBE_ID_LENGTH: usize = 11;
+    const SPOTIFY_ID_LENGTH: usize = 22;
+
+    fn name(self) -> &'static str {
+        match self {
+            TrackSource::Youtube => "YouTube",
+            TrackSource::Spotify => "Spotify"
+        }
+    }
+
+    /// Gets the source and ID of a track URL, URI or bare ID
+    fn parse(input: &str) -> Result<(Self, String), RejectReason> {
+        let input = input.trim();
+
+        if let Some(uri) = input.strip_prefix("spotify:") {
+            return match uri.split(':').collect::<Vec<_>>().as_slice() {
+                ["track", id] => Self::spotify_id(id),
+                _ => Err(RejectReason::NotATrack)
+            };
+        }
+
+        let url = match Url::parse(input) {
+            Ok(url) => url,
+            Err(_) if Self::is_youtube_id(input) => return Ok((TrackSource::Youtube, input.to_string())),
+            Err(_) if Self::is_spotify_id(input) => return Ok((TrackSource::Spotify, input.to_string())),
+            Err(_) => return Err(RejectReason::Unrecognized)
+        };
+
+        let host = url.host_str().unwrap_or_default();
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        let segments = url.path_segments().map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<_>>()).unwrap_or_default();
+
+        match host {
+            "youtube.com" | "m.youtube.com" | "music.youtube.com" => {
+                let id = match segments.as_slice() {
+                    ["watch"] => url.query_pairs().find(|(k, _)| k == "v").map(|(_, v)| v.into_owned()),
+                    ["shorts", id] | ["embed", id] => Some(id.to_string()),
+                    _ => return Err(RejectReason::NotATrack)
+                };
+
+                match id {
+                    Some(id) if Self::is_youtube_id(&id) => Ok((TrackSource::Youtube, id)),
+                    _ => Err(RejectReason::InvalidId)
+                }
+            },
+            "youtu.be" => match segments.as_slice() {
+                [id] if Self::is_youtube_id(id) => Ok((TrackSource::Youtube, id.to_string())),
+                _ => Err(RejectReason::InvalidId)
+            },
+            "open.spotify.com" => {
+                // Localized links look like open.spotify.com/intl-es/track/<id>
+                let segments = match segments.split_first() {
+                    Some((first, rest)) if first.starts_with("intl-") => rest,
+                    _ => &segments[..]
+                };
+
+                match segments {
+                    ["track", id] => Self::spotify_id(id),
+                    _ => Err(RejectReason::NotATrack)
+                }
+            },
+            _ => Err(RejectReason::Unrecognized)
+        }
+    }
+
+    fn spotify_id(id: &str) -> Result<(Self, String), RejectReason> {
+        if Self::is_spotify_id(id) {
+            Ok((TrackSource::Spotify, id.to_string()))
+        } else {
+            Err(RejectReason::InvalidId)
+        }
+    }
+
+    fn is_youtube_id(id: &str) -> bool {
+        id.len() == Self::YOUTUBE_ID_LENGTH && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    fn is_spotify_id(id: &str) -> bool {
+        id.len() == Self::SPOTIFY_ID_LENGTH && id.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+}
+
+/// Why an input was rejected by [`ProviderType::from_urls`](ProviderType::from_urls)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Not a YouTube or Spotify URL, URI or ID
+    Unrecognized,
+    /// A YouTube or Spotify link that does not point to a track, like a playlist or an album
+    NotATrack,
+    /// The link does not contain a valid track ID
+    InvalidId,
+    /// A track from another provider than the previous inputs
+    MixedProviders {
+        expected: &'static str,
+        found: &'static str
+    }
+}
+
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            RejectReason::Unrecognized => write!(f, "not a YouTube or Spotify track"),
+            RejectReason::NotATrack => write!(f, "does not point to a track"),
+            RejectReason::InvalidId => write!(f, "invalid track ID"),
+            RejectReason::MixedProviders { expected, found } => write!(f, "{} track in a list of {} tracks", found, expected)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedInput {
+    pub input: String,
+    pub reason: RejectReason
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProviderError {
+    /// No inputs were given
+    Empty,
+    Rejected(Vec<RejectedInput>)
+}
+
+impl Error for ProviderError {}
+
+impl Display for ProviderError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ProviderError::Empty => write!(f, "No tracks were given"),
+            ProviderError::Rejected(rejected) => {
+                write!(f, "Rejected {} track(s): ", rejected.len())?;
+                for (i, r) in rejected.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{:?} ({})", r.input, r.reason)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Type of the recommendations returned by the API
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecommendType {
+    Youtube,
+    YoutubeIDs,
+    YoutubeTitles,
+    Spotify
+}
+
+impl Display for RecommendType {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            RecommendType::Youtube => write!(f, "youtube"),
+            RecommendType::YoutubeIDs => write!(f, "youtube_ids"),
+            RecommendType::YoutubeTitles => write!(f, "youtube_titles"),
+            RecommendType::Spotify => write!(f, "spotify")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SpanType {
     Hour,
     Day,
@@ -57,3 +284,92 @@ impl Display for SpanType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(inputs: &[&str]) -> Result<(String, Vec<String>), ProviderError> {
+        match ProviderType::from_urls(inputs)? {
+            ProviderType::YoutubeIDs(ids) => Ok(("youtube_ids".to_string(), ids)),
+            ProviderType::SpotifyIDs(ids) => Ok(("spotify".to_string(), ids)),
+            other => Ok((other.to_string(), Vec::new()))
+        }
+    }
+
+    fn rejected(input: &str, reason: RejectReason) -> RejectedInput {
+        RejectedInput {
+            input: input.to_string(),
+            reason
+        }
+    }
+
+    #[test]
+    fn youtube_inputs() {
+        let provider = ids(&[
+            "https://www.youtube.com/watch?v=kJQP7kiw5Fk&list=RDkJQP7kiw5Fk&index=1",
+            "https://youtu.be/9bZkp7q19f0?t=42",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?v=fJ9rUzIMcZQ",
+            "https://www.youtube.com/embed/JGwWNGJdvx8",
+            "  OPf0YbXqDm0 "
+        ]);
+
+        assert_eq!(provider, Ok(("youtube_ids".to_string(), vec![
+            "kJQP7kiw5Fk".to_string(),
+            "9bZkp7q19f0".to_string(),
+            "dQw4w9WgXcQ".to_string(),
+            "fJ9rUzIMcZQ".to_string(),
+            "JGwWNGJdvx8".to_string(),
+            "OPf0YbXqDm0".to_string()
+        ])));
+    }
+
+    #[test]
+    fn spotify_inputs() {
+        let provider = ids(&[
+            "https://open.spotify.com/track/6habFhsOp2NvshLv26DqMb?si=1c2f3a4b5d6e7f80",
+            "https://open.spotify.com/intl-es/track/0VjIjW4GlUZAMYd2vXMi3b",
+            "spotify:track:7qiZfU4dY1lWllzX7mPBI3",
+            "3n3Ppam7vgaVa1iaRUc9Lp"
+        ]);
+
+        assert_eq!(provider, Ok(("spotify".to_string(), vec![
+            "6habFhsOp2NvshLv26DqMb".to_string(),
+            "0VjIjW4GlUZAMYd2vXMi3b".to_string(),
+            "7qiZfU4dY1lWllzX7mPBI3".to_string(),
+            "3n3Ppam7vgaVa1iaRUc9Lp".to_string()
+        ])));
+    }
+
+    #[test]
+    fn rejected_inputs() {
+        assert_eq!(ids(&[]), Err(ProviderError::Empty));
+
+        let provider = ids(&[
+            "https://www.youtube.com/watch?v=kJQP7kiw5Fk",
+            "https://open.spotify.com/track/6habFhsOp2NvshLv26DqMb",
+            "https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI",
+            "https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy",
+            "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            "https://www.youtube.com/watch?v=short",
+            "https://youtu.be/",
+            "https://soundcloud.com/artist/track",
+            "Despacito"
+        ]);
+
+        assert_eq!(provider, Err(ProviderError::Rejected(vec![
+            rejected("https://open.spotify.com/track/6habFhsOp2NvshLv26DqMb", RejectReason::MixedProviders {
+                expected: "YouTube",
+                found: "Spotify"
+            }),
+            rejected("https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI", RejectReason::NotATrack),
+            rejected("https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy", RejectReason::NotATrack),
+            rejected("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M", RejectReason::NotATrack),
+            rejected("https://www.youtube.com/watch?v=short", RejectReason::InvalidId),
+            rejected("https://youtu.be/", RejectReason::InvalidId),
+            rejected("https://soundcloud.com/artist/track", RejectReason::Unrecognized),
+            rejected("Despacito", RejectReason::Unrecognized)
+        ])));
+    }
+}
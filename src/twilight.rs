@@ -0,0 +1,125 @@
+//! Helpers to use KSoft.rs alongside the [twilight](https://twilight.rs) ecosystem
+//!
+//! Twilight user ids can be passed directly to any ban method taking an `user_id`
+use twilight_model::{
+    id::{marker::UserMarker, Id},
+    channel::message::embed::{Embed, EmbedAuthor, EmbedField, EmbedFooter, EmbedImage, EmbedThumbnail},
+    util::Timestamp
+};
+use crate::model::bans::{BanInfoResponse, UserId};
+
+#[cfg(feature = "default")]
+use twilight_model::gateway::payload::incoming::MemberAdd;
+#[cfg(feature = "default")]
+use crate::{
+    bans::Bans,
+    model::BanError,
+    HttpResult
+};
+
+/// Outcome of checking a member that just joined a guild against KSoft bans
+#[derive(Clone, Debug)]
+pub enum BanVerdict {
+    /// The user has no ban registered on KSoft
+    Clean,
+    /// The user has an active ban
+    Banned(BanInfoResponse),
+    /// The user was banned, but the ban is no longer active
+    Inactive(BanInfoResponse)
+}
+
+impl UserId for Id<UserMarker> {
+    fn user_id(self) -> u64 {
+        self.get()
+    }
+}
+
+impl BanVerdict {
+    /// Returns true if the user has an active ban
+    pub fn is_banned(&self) -> bool {
+        matches!(self, Self::Banned(_))
+    }
+}
+
+#[cfg(feature = "default")]
+impl Bans {
+    ///Checks the user of a twilight `MemberAdd` event against KSoft bans
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Event::MemberAdd(member) = event {
+    ///     if let Ok(Ok(verdict)) = client.bans.member_verdict(&member).await {
+    ///         if verdict.is_banned() {
+    ///             //kick or ban the member
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn member_verdict(&self, event: &MemberAdd) -> HttpResult<BanVerdict, BanError> {
+        Ok(match self.ban_info(event.user.id).await? {
+            Ok(info) if info.is_ban_active => Ok(BanVerdict::Banned(info)),
+            Ok(info) => Ok(BanVerdict::Inactive(info)),
+            Err(why) if why.code == 404 || why.exists == Some(false) => Ok(BanVerdict::Clean),
+            Err(why) => Err(why)
+        })
+    }
+}
+
+///Builds a twilight embed with the information of a ban
+///
+/// # Example
+///
+/// ```rust,ignore
+/// if let Ok(Ok(ban)) = client.bans.ban_info(user_id).await {
+///     http.create_message(channel_id).embeds(&[ban_info_embed(&ban)]).await?;
+/// }
+/// ```
+pub fn ban_info_embed(info: &BanInfoResponse) -> Embed {
+    crate::embed::Embed::from(info).into()
+}
+
+impl From<crate::embed::Embed> for Embed {
+    fn from(embed: crate::embed::Embed) -> Self {
+        Embed {
+            author: embed.author.map(|name| EmbedAuthor {
+                icon_url: None,
+                name,
+                proxy_icon_url: None,
+                url: None
+            }),
+            color: embed.color,
+            description: embed.description,
+            fields: embed.fields.into_iter()
+                .map(|f| EmbedField {
+                    inline: f.inline,
+                    name: f.name,
+                    value: f.value
+                })
+                .collect(),
+            footer: embed.footer.map(|text| EmbedFooter {
+                icon_url: None,
+                proxy_icon_url: None,
+                text
+            }),
+            image: embed.image.map(|url| EmbedImage {
+                height: None,
+                proxy_url: None,
+                url,
+                width: None
+            }),
+            kind: "rich".to_string(),
+            provider: None,
+            thumbnail: embed.thumbnail.map(|url| EmbedThumbnail {
+                height: None,
+                proxy_url: None,
+                url,
+                width: None
+            }),
+            timestamp: embed.timestamp.and_then(|t| Timestamp::parse(&t).ok()),
+            title: embed.title,
+            url: embed.url,
+            video: None
+        }
+    }
+}